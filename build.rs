@@ -83,14 +83,19 @@ fn cfg_for_target(target: &str) -> String {
             other_keys_fmt.push_str("]");
 
             format!("TargetInfo {{ \
+                        name: B({:?}), \
                         arch: B({:?}), \
                         os: B({:?}), \
                         env: B({:?}), \
                         endian: B({:?}), \
                         pointer_width: B({:?}), \
                         switches: B(&{}), \
-                        other_keys: B(&{}) \
-                    }}", arch, os, env, endian, ptrw, switches_fmt, other_keys_fmt)
+                        other_keys: B(&{}), \
+                        json: None, \
+                        spec: None, \
+                        path: None, \
+                        cpu: None \
+                    }}", target, arch, os, env, endian, ptrw, switches_fmt, other_keys_fmt)
         } else {
             println!("rustc --print=cfg --target={} did not exit successfully", target);
             String::new()
@@ -0,0 +1,245 @@
+//! ARM sub-architectures and floating point ABIs
+
+use TargetInfo;
+
+/// Version and profile of the ARM instruction set architecture
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ArmIsa {
+    /// ARMv4T
+    V4T,
+    /// ARMv5TE
+    V5TE,
+    /// ARMv6
+    V6,
+    /// ARMv6K
+    V6K,
+    /// ARMv6-M
+    V6M,
+    /// ARMv7-A
+    V7A,
+    /// ARMv7-R
+    V7R,
+    /// ARMv7-M
+    V7M,
+    /// ARMv7E-M
+    V7EM,
+    /// ARMv8-A, executing in the 32-bit AArch32 state
+    V8A,
+    /// ARMv8-R
+    V8R,
+    /// ARMv8-M Baseline
+    V8MBase,
+    /// ARMv8-M Mainline
+    V8MMain,
+    /// ARMv8.1-M Mainline
+    V81MMain,
+}
+
+impl ArmIsa {
+    /// Whether this is a microcontroller (M-profile) architecture, which only supports the Thumb
+    /// instruction set
+    pub fn is_m_profile(&self) -> bool {
+        match *self {
+            ArmIsa::V6M | ArmIsa::V7M | ArmIsa::V7EM | ArmIsa::V8MBase | ArmIsa::V8MMain |
+            ArmIsa::V81MMain => true,
+            _ => false,
+        }
+    }
+}
+
+/// Floating point ABI of ARM targets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatAbi {
+    /// Floating point operations are done in software and values are passed in integer
+    /// registers (`-mfloat-abi=soft`)
+    Soft,
+    /// Floating point instructions are used, but values are passed in integer registers
+    /// (`-mfloat-abi=softfp`)
+    SoftFp,
+    /// Floating point instructions are used and values are passed in floating point registers
+    /// (`-mfloat-abi=hard`)
+    Hard,
+}
+
+/// Sub-architecture information of ARM targets
+///
+/// See `TargetInfo::arm_info`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArmInfo {
+    /// Version and profile of the instruction set architecture, if known
+    pub isa: Option<ArmIsa>,
+    /// Whether the processor only supports the Thumb instruction set
+    pub thumb_only: bool,
+    /// Whether code is generated in the Thumb instruction set by default
+    pub thumb_mode: bool,
+    /// The floating point ABI
+    pub float_abi: FloatAbi,
+}
+
+/// Instruction set architecture named by the architecture component of the triple, without the
+/// `arm`, `armeb` or `thumb` prefix
+fn triple_isa(version: &str) -> Option<ArmIsa> {
+    match version {
+        "v4t" => Some(ArmIsa::V4T),
+        "v5te" => Some(ArmIsa::V5TE),
+        "v6" => Some(ArmIsa::V6),
+        "v6k" => Some(ArmIsa::V6K),
+        "v6m" => Some(ArmIsa::V6M),
+        "v7" | "v7a" | "v7neon" | "v7s" | "v7k" => Some(ArmIsa::V7A),
+        "v7r" => Some(ArmIsa::V7R),
+        "v7m" => Some(ArmIsa::V7M),
+        "v7em" => Some(ArmIsa::V7EM),
+        "v8" | "v8a" => Some(ArmIsa::V8A),
+        "v8r" => Some(ArmIsa::V8R),
+        "v8m.base" => Some(ArmIsa::V8MBase),
+        "v8m.main" => Some(ArmIsa::V8MMain),
+        "v8.1m.main" => Some(ArmIsa::V81MMain),
+        _ => None,
+    }
+}
+
+impl TargetInfo {
+    /// Enabled target features, including the `features` of custom JSON targets
+    fn arm_features(&self) -> Vec<String> {
+        let mut features: Vec<String> = self.target_cfg_values("target_feature").iter()
+                                            .map(|f| String::from(*f)).collect();
+        for feature in self.spec_value("features").unwrap_or_default().split(',') {
+            let mut chars = feature.chars();
+            match chars.next() {
+                Some('+') => features.push(chars.as_str().to_owned()),
+                Some('-') => features.retain(|f| f != chars.as_str()),
+                _ => {}
+            }
+        }
+        features
+    }
+
+    /// Sub-architecture information of ARM targets
+    ///
+    /// `target_arch` is `arm` for all the 32-bit ARM targets. This provides the version of the
+    /// instruction set architecture and the floating point ABI, which are derived from the
+    /// triple (or `llvm-target` of custom JSON targets), the `target_abi` and the target
+    /// features, including the `features` and `llvm-floatabi` of custom JSON targets.
+    ///
+    /// Returns `None` if the architecture of the target is not `arm`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::{ArmIsa, FloatAbi, TargetInfo};
+    /// let target = TargetInfo::from_str("thumbv7em-none-eabihf").expect("target info");
+    /// let arm = target.arm_info().unwrap();
+    /// assert_eq!(arm.isa, Some(ArmIsa::V7EM));
+    /// assert!(arm.thumb_only);
+    /// assert_eq!(arm.float_abi, FloatAbi::Hard);
+    /// ```
+    pub fn arm_info(&self) -> Option<ArmInfo> {
+        if self.target_arch() != "arm" {
+            return None;
+        }
+        let triple = self.llvm_triple();
+        let arch = triple.split('-').next().unwrap_or("");
+        let abi = triple.rsplit('-').next().unwrap_or("");
+        let features = self.arm_features();
+        let has_feature = |name: &str| features.iter().any(|f| f == name);
+
+        let thumb = arch.starts_with("thumb");
+        let version = arch.trim_start_matches("thumb").trim_start_matches("arm")
+                          .trim_start_matches("eb");
+        let isa = match triple_isa(version) {
+            Some(isa) => Some(isa),
+            None if version.is_empty() && self.target_os() == "android" => Some(ArmIsa::V5TE),
+            None if version.is_empty() => Some(ArmIsa::V6),
+            None => None,
+        };
+        let thumb_only = isa.map(|isa| isa.is_m_profile()).unwrap_or(has_feature("mclass"));
+
+        let target_abi = self.target_cfg_value("target_abi").unwrap_or("");
+        let has_fpu = features.iter().any(|f| f.starts_with("vfp") || f.starts_with("fp-armv8"));
+        let spec_float_abi = self.spec_value("llvm-floatabi");
+        let float_abi = match spec_float_abi.as_ref().map(|f| &**f) {
+            Some("hard") => FloatAbi::Hard,
+            None if abi.ends_with("hf") || target_abi.ends_with("hf") => FloatAbi::Hard,
+            _ if has_feature("soft-float") => FloatAbi::Soft,
+            // The soft float ABI of LLVM uses the floating point instructions, if available
+            Some(_) if has_fpu => FloatAbi::SoftFp,
+            Some(_) => FloatAbi::Soft,
+            None => match self.target_os() {
+                // Of the Apple targets only armv7k watchOS uses the hard-float ABI
+                "windows" | "watchos" => FloatAbi::Hard,
+                "android" | "ios" => FloatAbi::SoftFp,
+                _ if has_fpu => FloatAbi::SoftFp,
+                _ => FloatAbi::Soft,
+            },
+        };
+
+        Some(ArmInfo {
+            isa: isa,
+            thumb_only: thumb_only,
+            thumb_mode: thumb || has_feature("thumb-mode"),
+            float_abi: float_abi,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {ArmInfo, ArmIsa, FloatAbi, TargetInfo};
+
+    #[test]
+    fn arm_info() {
+        let arm = |isa, thumb_only, thumb_mode, float_abi| {
+            Some(ArmInfo {
+                isa: Some(isa),
+                thumb_only: thumb_only,
+                thumb_mode: thumb_mode,
+                float_abi: float_abi,
+            })
+        };
+        macro_rules! check_arm {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.arm_info(), $expected, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_arm!("arm-unknown-linux-gnueabi" => arm(ArmIsa::V6, false, false, FloatAbi::Soft),
+                   "arm-unknown-linux-gnueabihf" => arm(ArmIsa::V6, false, false, FloatAbi::Hard),
+                   "armv5te-unknown-linux-gnueabi" =>
+                       arm(ArmIsa::V5TE, false, false, FloatAbi::Soft),
+                   "armv7-unknown-linux-gnueabihf" =>
+                       arm(ArmIsa::V7A, false, false, FloatAbi::Hard),
+                   "armv7-linux-androideabi" => arm(ArmIsa::V7A, false, false, FloatAbi::SoftFp),
+                   "thumbv7neon-linux-androideabi" =>
+                       arm(ArmIsa::V7A, false, true, FloatAbi::SoftFp),
+                   "thumbv7a-pc-windows-msvc" => arm(ArmIsa::V7A, false, true, FloatAbi::Hard),
+                   "armv7s-apple-ios" => arm(ArmIsa::V7A, false, false, FloatAbi::SoftFp),
+                   "armv7k-apple-watchos" => arm(ArmIsa::V7A, false, false, FloatAbi::Hard),
+                   "armebv7r-none-eabihf" => arm(ArmIsa::V7R, false, false, FloatAbi::Hard),
+                   "thumbv6m-none-eabi" => arm(ArmIsa::V6M, true, true, FloatAbi::Soft),
+                   "thumbv7em-none-eabi" => arm(ArmIsa::V7EM, true, true, FloatAbi::Soft),
+                   "thumbv7em-none-eabihf" => arm(ArmIsa::V7EM, true, true, FloatAbi::Hard),
+                   "thumbv8m.base-none-eabi" => arm(ArmIsa::V8MBase, true, true, FloatAbi::Soft),
+                   "thumbv8m.main-none-eabihf" =>
+                       arm(ArmIsa::V8MMain, true, true, FloatAbi::Hard),
+                   "thumbv8.1m.main-none-eabi" =>
+                       arm(ArmIsa::V81MMain, true, true, FloatAbi::Soft),
+                   "aarch64-unknown-linux-gnu" => None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn arm_info_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "base-target": "armv7-unknown-linux-gnueabi",
+            "llvm-target": "armv7-unknown-linux-gnueabi",
+            "features": "+v7,+vfp3,-neon,+thumb-mode"
+        }"#).unwrap();
+        let arm = ti.arm_info().unwrap();
+        assert_eq!(arm.isa, Some(ArmIsa::V7A));
+        assert!(arm.thumb_mode);
+        assert_eq!(arm.float_abi, FloatAbi::SoftFp);
+    }
+}
@@ -0,0 +1,174 @@
+//! Naming of the libraries and executables produced for the target
+
+use std::borrow::Cow;
+
+use TargetInfo;
+
+impl TargetInfo {
+    /// Value of the specification field, or the default
+    fn spec_string(&self, key: &str, default: &'static str) -> Cow<'static, str> {
+        match self.spec_value(key) {
+            Some(v) => Cow::Owned(v),
+            None => Cow::Borrowed(default),
+        }
+    }
+
+    /// Whether the target is WebAssembly
+    fn is_wasm(&self) -> bool {
+        self.target_arch() == "wasm32" || self.target_arch() == "wasm64"
+    }
+
+    /// Prefix of dynamic library file names, e.g. `lib` in `libfoo.so`
+    ///
+    /// This is the `dll-prefix` field of custom JSON targets, if present.
+    pub fn dylib_prefix(&self) -> Cow<'static, str> {
+        let default = if self.is_like_windows() || self.is_wasm() { "" } else { "lib" };
+        self.spec_string("dll-prefix", default)
+    }
+
+    /// Suffix of dynamic library file names, e.g. `.so`, `.dylib` or `.dll`
+    ///
+    /// This is the `dll-suffix` field of custom JSON targets, if present.
+    pub fn dylib_suffix(&self) -> Cow<'static, str> {
+        let default = if self.is_like_windows() {
+            ".dll"
+        } else if self.is_like_osx() {
+            ".dylib"
+        } else if self.is_wasm() {
+            ".wasm"
+        } else {
+            ".so"
+        };
+        self.spec_string("dll-suffix", default)
+    }
+
+    /// Prefix of static library file names, e.g. `lib` in `libfoo.a`
+    ///
+    /// This is the `staticlib-prefix` field of custom JSON targets, if present.
+    pub fn staticlib_prefix(&self) -> Cow<'static, str> {
+        let default = if self.is_like_msvc() { "" } else { "lib" };
+        self.spec_string("staticlib-prefix", default)
+    }
+
+    /// Suffix of static library file names, e.g. `.a` or `.lib`
+    ///
+    /// This is the `staticlib-suffix` field of custom JSON targets, if present.
+    pub fn staticlib_suffix(&self) -> Cow<'static, str> {
+        let default = if self.is_like_msvc() { ".lib" } else { ".a" };
+        self.spec_string("staticlib-suffix", default)
+    }
+
+    /// Suffix of executable file names, e.g. `.exe`, or an empty string
+    ///
+    /// This is the `exe-suffix` field of custom JSON targets, if present.
+    pub fn exe_suffix(&self) -> Cow<'static, str> {
+        let default = match self.target_os() {
+            "uefi" => ".efi",
+            "emscripten" => ".js",
+            _ if self.is_like_windows() => ".exe",
+            _ if self.is_wasm() => ".wasm",
+            _ => "",
+        };
+        self.spec_string("exe-suffix", default)
+    }
+
+    /// File name of the dynamic library with the given name, e.g. `libfoo.so` for `foo`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("x86_64-apple-darwin").expect("target info");
+    /// assert_eq!(target.dylib_name("foo"), "libfoo.dylib");
+    /// ```
+    pub fn dylib_name(&self, name: &str) -> String {
+        format!("{}{}{}", self.dylib_prefix(), name, self.dylib_suffix())
+    }
+
+    /// File name of the static library with the given name, e.g. `libfoo.a` for `foo`
+    pub fn staticlib_name(&self, name: &str) -> String {
+        format!("{}{}{}", self.staticlib_prefix(), name, self.staticlib_suffix())
+    }
+
+    /// File name of the executable with the given name, e.g. `foo.exe` for `foo`
+    pub fn exe_name(&self, name: &str) -> String {
+        format!("{}{}", name, self.exe_suffix())
+    }
+
+    /// File name of the import library for the dynamic library with the given name
+    ///
+    /// Import libraries are only used on Windows-like targets, for which this is
+    /// `{name}.dll.lib` with the MSVC toolchain and `lib{name}.dll.a` with MinGW and Cygwin,
+    /// the same as the import libraries rustc produces for `cdylib` crates. `None` is returned
+    /// for other targets.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("x86_64-pc-windows-gnu").expect("target info");
+    /// assert_eq!(target.import_lib_name("foo").unwrap(), "libfoo.dll.a");
+    /// ```
+    pub fn import_lib_name(&self, name: &str) -> Option<String> {
+        if !self.is_like_windows() {
+            return None;
+        }
+        Some(format!("{}{}{}{}", self.staticlib_prefix(), name, self.dylib_suffix(),
+                     self.staticlib_suffix()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use TargetInfo;
+
+    #[test]
+    fn artifact_names() {
+        macro_rules! check_names {
+            ($($str: expr => ($dylib: expr, $staticlib: expr, $exe: expr, $import_lib: expr)),+)
+            => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.dylib_name("foo"), $dylib, "{}", $str);
+                        assert_eq!(ti.staticlib_name("foo"), $staticlib, "{}", $str);
+                        assert_eq!(ti.exe_name("foo"), $exe, "{}", $str);
+                        assert_eq!(ti.import_lib_name("foo").as_ref().map(|s| &**s), $import_lib,
+                                   "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_names!("x86_64-unknown-linux-gnu" => ("libfoo.so", "libfoo.a", "foo", None),
+                     "aarch64-apple-darwin" => ("libfoo.dylib", "libfoo.a", "foo", None),
+                     "x86_64-pc-windows-msvc" =>
+                         ("foo.dll", "foo.lib", "foo.exe", Some("foo.dll.lib")),
+                     "x86_64-pc-windows-gnu" =>
+                         ("foo.dll", "libfoo.a", "foo.exe", Some("libfoo.dll.a")),
+                     "wasm32-unknown-unknown" => ("foo.wasm", "libfoo.a", "foo.wasm", None),
+                     "x86_64-unknown-uefi" =>
+                         ("foo.dll", "foo.lib", "foo.efi", Some("foo.dll.lib")),
+                     "x86_64-pc-cygwin" => ("foo.dll", "libfoo.a", "foo.exe", Some("libfoo.dll.a")),
+                     "wasm32-unknown-emscripten" => ("foo.wasm", "libfoo.a", "foo.js", None));
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn artifact_names_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "base-target": "x86_64-unknown-linux-gnu",
+            "dll-suffix": ".so.1",
+            "exe-suffix": ".elf"
+        }"#).unwrap();
+        assert_eq!(ti.dylib_name("foo"), "libfoo.so.1");
+        assert_eq!(ti.exe_name("foo"), "foo.elf");
+
+        let ti = TargetInfo::from_json_str(r#"{
+            "arch": "aarch64",
+            "os": "myos",
+            "target-endian": "little",
+            "target-pointer-width": 64,
+            "is-like-darwin": true
+        }"#).unwrap();
+        assert_eq!(ti.dylib_name("foo"), "libfoo.dylib");
+    }
+}
@@ -0,0 +1,192 @@
+//! Interaction with cargo from within build scripts
+
+use std::env;
+use std::ffi::OsString;
+
+use TargetInfo;
+
+impl TargetInfo {
+    /// Names of the target-scoped variants of the environment variable, in order of precedence
+    ///
+    /// This is the same order the `cc` crate uses:
+    ///
+    /// 1. `{name}_{target}`, e.g. `CC_armv7-unknown-linux-gnueabihf`;
+    /// 2. `{name}_{target_with_underscores}`, e.g. `CC_armv7_unknown_linux_gnueabihf`, where
+    ///    both `-` and `.` are replaced with `_`;
+    /// 3. `TARGET_{name}` when cross-compiling, or `HOST_{name}` otherwise, as determined by
+    ///    comparing the target triple with the `HOST` environment variable;
+    /// 4. `{name}`.
+    pub fn target_env_var_names(&self, name: &str) -> Vec<String> {
+        let triple = self.target_triple();
+        let kind = match env::var("HOST") {
+            Ok(ref host) if host == triple => "HOST",
+            _ => "TARGET",
+        };
+        vec![format!("{}_{}", name, triple),
+             format!("{}_{}", name, triple.replace(|c| c == '-' || c == '.', "_")),
+             format!("{}_{}", kind, name),
+             String::from(name)]
+    }
+
+    /// Look up a target-scoped environment variable
+    ///
+    /// Returns the value of the first set variable out of the
+    /// [`target_env_var_names`](#method.target_env_var_names). The target triple of custom JSON
+    /// targets is the name of the file, just like with `TARGET` set by cargo.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// let cc = target.target_env_var("CC").unwrap_or("cc".into());
+    /// let cflags = target.target_env_var("CFLAGS");
+    /// ```
+    pub fn target_env_var(&self, name: &str) -> Option<OsString> {
+        self.target_env_var_names(name).iter().filter_map(env::var_os).next()
+    }
+
+    /// Name of the `CARGO_TARGET_{TRIPLE}_{KEY}` environment variable for the target
+    ///
+    /// These variables configure cargo for a specific target, e.g.
+    /// `CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_LINKER`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("x86_64-unknown-linux-gnu").expect("target info");
+    /// assert_eq!(target.cargo_target_env_var_name("runner"),
+    ///            "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUNNER");
+    /// ```
+    pub fn cargo_target_env_var_name(&self, key: &str) -> String {
+        let mangle = |s: &str| -> String {
+            s.chars().map(|c| match c {
+                '-' | '.' => '_',
+                c => c.to_ascii_uppercase(),
+            }).collect()
+        };
+        format!("CARGO_TARGET_{}_{}", mangle(self.target_triple()), mangle(key))
+    }
+
+    /// Value of the `CARGO_TARGET_{TRIPLE}_{KEY}` environment variable for the target
+    pub fn cargo_target_env_var(&self, key: &str) -> Option<OsString> {
+        env::var_os(self.cargo_target_env_var_name(key))
+    }
+
+    /// Linker configured for the target via `CARGO_TARGET_{TRIPLE}_LINKER`
+    pub fn cargo_target_linker(&self) -> Option<OsString> {
+        self.cargo_target_env_var("linker")
+    }
+
+    /// Runner configured for the target via `CARGO_TARGET_{TRIPLE}_RUNNER`
+    ///
+    /// The runner is a command followed by its arguments, separated by spaces.
+    pub fn cargo_target_runner(&self) -> Option<OsString> {
+        self.cargo_target_env_var("runner")
+    }
+
+    /// Flags configured for the target via `CARGO_TARGET_{TRIPLE}_RUSTFLAGS`
+    ///
+    /// The flags are separated by spaces.
+    pub fn cargo_target_rustflags(&self) -> Option<OsString> {
+        self.cargo_target_env_var("rustflags")
+    }
+
+    /// Print the `cargo:rerun-if-*` directives relevant to the target resolution
+    ///
+    /// This makes cargo rerun the build script whenever `TARGET`, `RUST_TARGET_PATH` or `RUSTC`
+    /// environment variables change, as well as when the custom target JSON file this target
+    /// was loaded from is modified.
+    ///
+    /// Note that once a build script prints any `rerun-if` directive, cargo stops rerunning it
+    /// on changes to the package sources.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// target.emit_rerun_directives();
+    /// ```
+    pub fn emit_rerun_directives(&self) {
+        for directive in self.rerun_directives() {
+            println!("{}", directive);
+        }
+    }
+
+    fn rerun_directives(&self) -> Vec<String> {
+        let mut directives = vec![
+            String::from("cargo:rerun-if-env-changed=TARGET"),
+            String::from("cargo:rerun-if-env-changed=RUST_TARGET_PATH"),
+            String::from("cargo:rerun-if-env-changed=RUSTC"),
+        ];
+        if let Some(path) = self.target_json_path() {
+            directives.push(format!("cargo:rerun-if-changed={}", path.display()));
+        }
+        directives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use TargetInfo;
+
+    #[test]
+    fn target_env_var() {
+        if let Ok(ti) = TargetInfo::from_str("armv7-unknown-linux-gnueabihf") {
+            assert_eq!(ti.target_env_var_names("TBU_TEST_CC")[..2],
+                       ["TBU_TEST_CC_armv7-unknown-linux-gnueabihf",
+                        "TBU_TEST_CC_armv7_unknown_linux_gnueabihf"]);
+            assert_eq!(ti.target_env_var("TBU_TEST_CC"), None);
+            env::set_var("TBU_TEST_CC", "cc");
+            assert_eq!(ti.target_env_var("TBU_TEST_CC"), Some("cc".into()));
+            env::set_var("TARGET_TBU_TEST_CC", "target-cc");
+            assert_eq!(ti.target_env_var("TBU_TEST_CC"), Some("target-cc".into()));
+            env::set_var("TBU_TEST_CC_armv7_unknown_linux_gnueabihf", "underscore-cc");
+            assert_eq!(ti.target_env_var("TBU_TEST_CC"), Some("underscore-cc".into()));
+            env::set_var("TBU_TEST_CC_armv7-unknown-linux-gnueabihf", "triple-cc");
+            assert_eq!(ti.target_env_var("TBU_TEST_CC"), Some("triple-cc".into()));
+        }
+        if let Ok(ti) = TargetInfo::from_str("thumbv8m.main-none-eabihf") {
+            assert_eq!(ti.target_env_var_names("CC")[..2],
+                       ["CC_thumbv8m.main-none-eabihf", "CC_thumbv8m_main_none_eabihf"]);
+        }
+    }
+
+    #[test]
+    fn cargo_target_env_var() {
+        if let Ok(ti) = TargetInfo::from_str("thumbv7em-none-eabihf") {
+            assert_eq!(ti.cargo_target_env_var_name("linker"),
+                       "CARGO_TARGET_THUMBV7EM_NONE_EABIHF_LINKER");
+            assert_eq!(ti.cargo_target_runner(), None);
+            env::set_var("CARGO_TARGET_THUMBV7EM_NONE_EABIHF_RUNNER", "probe-rs run");
+            assert_eq!(ti.cargo_target_runner(), Some("probe-rs run".into()));
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            assert_eq!(ti.cargo_target_env_var_name("rustflags"),
+                       "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUSTFLAGS");
+        }
+    }
+
+    #[test]
+    fn rerun_directives() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            assert_eq!(ti.rerun_directives(), ["cargo:rerun-if-env-changed=TARGET",
+                                               "cargo:rerun-if-env-changed=RUST_TARGET_PATH",
+                                               "cargo:rerun-if-env-changed=RUSTC"]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn rerun_directives_json() {
+        let ti = TargetInfo::from_str("src/my-great-target.json").unwrap();
+        let directives = ti.rerun_directives();
+        assert_eq!(directives.len(), 4);
+        assert!(directives[3].starts_with("cargo:rerun-if-changed="));
+        assert!(directives[3].ends_with("my-great-target.json"));
+        assert!(ti.target_json_path().unwrap().is_absolute());
+    }
+}
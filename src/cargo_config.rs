@@ -0,0 +1,306 @@
+//! Target configuration from cargo configuration files
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use {Error, TargetInfo};
+
+/// Target configuration collected from `.cargo/config.toml` files
+///
+/// See `TargetInfo::cargo_config`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CargoTargetConfig {
+    /// The `linker` to use for the target
+    ///
+    /// Relative paths containing a directory separator are resolved against the parent of the
+    /// `.cargo` directory the value was read from.
+    pub linker: Option<PathBuf>,
+    /// The `runner` command and its arguments
+    pub runner: Option<Vec<String>>,
+    /// All the `rustflags` for the target
+    pub rustflags: Vec<String>,
+}
+
+/// A configuration value along with the directory containing the `.cargo` directory it was read
+/// from
+#[derive(Clone, Debug)]
+struct Located<T> {
+    value: T,
+    root: PathBuf,
+}
+
+/// Values read from a `[target]` section, merged across the configuration files
+#[derive(Debug, Default)]
+struct Section {
+    linker: Option<Located<String>>,
+    runner: Option<Located<Vec<String>>>,
+    rustflags: Vec<String>,
+}
+
+/// Values read from the `[target]` sections matching the target
+#[derive(Debug, Default)]
+struct Collected {
+    triple: Section,
+    // Keyed by the name of the section, as cargo orders them
+    cfgs: BTreeMap<String, Section>,
+}
+
+/// Configuration files in increasing order of precedence
+///
+/// Cargo reads `.cargo/config.toml` (or the legacy `.cargo/config`, if it exists) in every
+/// ancestor of the starting directory and in `CARGO_HOME`. Files closer to the starting
+/// directory take precedence.
+fn config_files(start: &Path, cargo_home: Option<&Path>) -> Vec<(PathBuf, PathBuf)> {
+    fn config_in(dot_cargo: &Path) -> Option<PathBuf> {
+        let legacy = dot_cargo.join("config");
+        let toml = dot_cargo.join("config.toml");
+        if legacy.is_file() {
+            Some(legacy)
+        } else if toml.is_file() {
+            Some(toml)
+        } else {
+            None
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut home_seen = false;
+    for dir in start.ancestors() {
+        let dot_cargo = dir.join(".cargo");
+        if cargo_home.map(|h| h == dot_cargo.as_path()).unwrap_or(false) {
+            home_seen = true;
+        }
+        if let Some(file) = config_in(&dot_cargo) {
+            files.push((file, dir.to_path_buf()));
+        }
+    }
+    if let (Some(home), false) = (cargo_home, home_seen) {
+        if let Some(file) = config_in(home) {
+            let root = home.parent().unwrap_or(home).to_path_buf();
+            files.push((file, root));
+        }
+    }
+    files.reverse();
+    files
+}
+
+/// `CARGO_HOME`, defaulting to `.cargo` in the home directory
+fn cargo_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    env::var_os("HOME").or(env::var_os("USERPROFILE"))
+                       .map(|home| PathBuf::from(home).join(".cargo"))
+}
+
+/// Resolve a path configured relative to the configuration file like cargo does
+fn config_relative(value: &str, root: &Path) -> PathBuf {
+    let path = Path::new(value);
+    if path.is_relative() && (value.contains('/') || value.contains('\\')) {
+        root.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(feature = "toml")]
+fn collect(target: &TargetInfo, files: &[(PathBuf, PathBuf)]) -> Result<Collected, Error> {
+    use std::fs::File;
+    use std::io::Read;
+    use toml::Value;
+
+    // Strings are split on whitespace, arrays must contain only strings
+    fn string_list(value: &Value) -> Result<Vec<String>, Error> {
+        match *value {
+            Value::String(ref s) => Ok(s.split_whitespace().map(String::from).collect()),
+            Value::Array(ref a) => a.iter().map(|v| {
+                v.as_str().map(String::from).ok_or(Error::InvalidCargoConfig)
+            }).collect(),
+            _ => Err(Error::InvalidCargoConfig),
+        }
+    }
+
+    let mut collected = Collected::default();
+    for &(ref file, ref root) in files {
+        let mut contents = String::new();
+        try!(File::open(file).and_then(|mut f| f.read_to_string(&mut contents))
+                             .map_err(Error::Io));
+        let config: Value = try!(contents.parse().map_err(|_| Error::InvalidCargoConfig));
+        let sections = match config.get("target") {
+            Some(&Value::Table(ref sections)) => sections,
+            Some(_) => return Err(Error::InvalidCargoConfig),
+            None => continue,
+        };
+        for (key, section) in sections {
+            let is_cfg = key.starts_with("cfg(") && key.ends_with(')');
+            if is_cfg {
+                let cfg = try!(key[4..key.len() - 1].parse());
+                if !target.target_cfg_matches(&cfg) {
+                    continue;
+                }
+            } else if key != target.target_triple() {
+                continue;
+            }
+            let section = try!(section.as_table().ok_or(Error::InvalidCargoConfig));
+            let merged = if is_cfg {
+                collected.cfgs.entry(key.clone()).or_insert_with(Section::default)
+            } else {
+                &mut collected.triple
+            };
+            // Files closer to the starting directory come later and take precedence
+            if let Some(linker) = section.get("linker") {
+                merged.linker = Some(Located {
+                    value: try!(linker.as_str().ok_or(Error::InvalidCargoConfig)).to_owned(),
+                    root: root.clone(),
+                });
+            }
+            if let Some(runner) = section.get("runner") {
+                merged.runner = Some(Located { value: try!(string_list(runner)),
+                                               root: root.clone() });
+            }
+            if let Some(rustflags) = section.get("rustflags") {
+                merged.rustflags.extend(try!(string_list(rustflags)));
+            }
+        }
+    }
+    Ok(collected)
+}
+
+#[cfg(not(feature = "toml"))]
+fn collect(_: &TargetInfo, _: &[(PathBuf, PathBuf)]) -> Result<Collected, Error> {
+    Err(Error::CargoConfigUnsupported)
+}
+
+/// Pick the value set for the target triple, or the only value set in a matching `cfg` section
+fn pick<T, I>(triple: Option<Located<T>>, cfg: I) -> Result<Option<Located<T>>, Error>
+where I: Iterator<Item = Located<T>> {
+    let mut cfg: Vec<_> = cfg.collect();
+    match (triple, cfg.len()) {
+        (Some(v), _) => Ok(Some(v)),
+        (None, 0) => Ok(None),
+        (None, 1) => Ok(cfg.pop()),
+        (None, _) => Err(Error::InvalidCargoConfig),
+    }
+}
+
+impl TargetInfo {
+    /// Read the configuration for the target from cargo configuration files
+    ///
+    /// The configuration files are found by walking up from `CARGO_MANIFEST_DIR` (or the
+    /// current directory, if it is not set) and in `CARGO_HOME`, and are merged the way cargo
+    /// does: values from files closer to the manifest take precedence, and `rustflags` from all
+    /// of the files are joined together. Both `[target.{triple}]` and the matching
+    /// `[target.'cfg(…)']` sections are considered, with the former taking precedence for
+    /// `linker` and `runner`. The `rustflags` of the `[target.{triple}]` sections come first,
+    /// followed by those of the matching `cfg` sections in the order of their names.
+    ///
+    /// The `CARGO_TARGET_{TRIPLE}_LINKER` and `CARGO_TARGET_{TRIPLE}_RUNNER` environment
+    /// variables override the configuration files, and `CARGO_TARGET_{TRIPLE}_RUSTFLAGS` is
+    /// appended to the `rustflags` of the `[target.{triple}]` sections.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// let config = target.cargo_config().expect("could not read cargo configuration");
+    /// if let Some(linker) = config.linker {
+    ///     println!("linking with {}", linker.display());
+    /// }
+    /// ```
+    pub fn cargo_config(&self) -> Result<CargoTargetConfig, Error> {
+        let start = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => try!(env::current_dir().map_err(Error::Io)),
+        };
+        let cargo_home = cargo_home();
+        self.cargo_config_from(&start, cargo_home.as_ref().map(|p| &**p))
+    }
+
+    fn cargo_config_from(&self, start: &Path, cargo_home: Option<&Path>)
+    -> Result<CargoTargetConfig, Error> {
+        let collected = try!(collect(self, &config_files(start, cargo_home)));
+        let Collected { triple, cfgs } = collected;
+        let linker = try!(pick(triple.linker, cfgs.values().filter_map(|s| s.linker.clone())));
+        let runner = try!(pick(triple.runner, cfgs.values().filter_map(|s| s.runner.clone())));
+        let mut config = CargoTargetConfig {
+            linker: linker.map(|l| config_relative(&l.value, &l.root)),
+            runner: runner.map(|r| {
+                let mut value = r.value;
+                if let Some(program) = value.first_mut() {
+                    *program = config_relative(program, &r.root).to_string_lossy().into_owned();
+                }
+                value
+            }),
+            rustflags: triple.rustflags,
+        };
+        if let Some(linker) = self.cargo_target_linker() {
+            config.linker = Some(PathBuf::from(linker));
+        }
+        if let Some(runner) = self.cargo_target_runner() {
+            config.runner = Some(runner.to_string_lossy().split_whitespace()
+                                       .map(String::from).collect());
+        }
+        if let Some(rustflags) = self.cargo_target_rustflags() {
+            config.rustflags.extend(rustflags.to_string_lossy().split_whitespace()
+                                             .map(String::from));
+        }
+        for section in cfgs.values() {
+            config.rustflags.extend(section.rustflags.iter().cloned());
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "toml")]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use TargetInfo;
+
+    #[test]
+    fn cargo_config() {
+        let ti = match TargetInfo::from_str("armv7-unknown-linux-gnueabihf") {
+            Ok(ti) => ti,
+            Err(_) => return,
+        };
+        let root = env::temp_dir().join(format!("target_build_utils-{}", ::std::process::id()));
+        let project = root.join("workspace").join("project");
+        let home = root.join("home");
+        for dir in &[project.join(".cargo"), root.join("workspace/.cargo"), home.clone()] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(home.join("config.toml"), "\
+            [target.armv7-unknown-linux-gnueabihf]\n\
+            linker = \"arm-linux-gnueabihf-gcc\"\n\
+            rustflags = [\"-Chome\"]\n\
+            [target.'cfg(target_arch = \"arm\")']\n\
+            runner = \"qemu-arm\"\n\
+            rustflags = [\"-Chome-cfg\"]\n\
+            [target.'cfg(unix)']\n\
+            rustflags = [\"-Cunix\"]\n").unwrap();
+        fs::write(root.join("workspace/.cargo/config.toml"), "\
+            [target.'cfg(target_arch = \"arm\")']\n\
+            runner = \"tools/qemu-arm -L /usr/arm-linux-gnueabihf\"\n\
+            rustflags = \"-Cworkspace\"\n\
+            [target.'cfg(windows)']\n\
+            runner = \"wine\"\n").unwrap();
+        fs::write(project.join(".cargo/config.toml"), "\
+            [target.armv7-unknown-linux-gnueabihf]\n\
+            rustflags = [\"-Cproject\"]\n\
+            [target.x86_64-unknown-linux-gnu]\n\
+            linker = \"clang\"\n").unwrap();
+
+        let config = ti.cargo_config_from(&project, Some(&home)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(config.linker, Some(PathBuf::from("arm-linux-gnueabihf-gcc")));
+        let qemu = root.join("workspace").join("tools/qemu-arm");
+        assert_eq!(config.runner, Some(vec![qemu.to_string_lossy().into_owned(), "-L".into(),
+                                            "/usr/arm-linux-gnueabihf".into()]));
+        assert_eq!(config.rustflags, ["-Chome", "-Cproject", "-Chome-cfg", "-Cworkspace",
+                                      "-Cunix"]);
+    }
+}
@@ -0,0 +1,330 @@
+//! Evaluation of `cfg` predicates against a target
+
+use std::fmt;
+use std::str::FromStr;
+
+use {Error, TargetInfo};
+
+/// A parsed `cfg` predicate
+///
+/// The syntax is the same as the one accepted by `#[cfg(…)]` attributes in Rust code, e.g.
+/// `all(unix, not(target_os = "macos"))`.
+///
+/// # Examples
+///
+/// ```rust
+/// use target_build_utils::Cfg;
+/// let cfg: Cfg = "any(target_os = \"linux\", target_os = \"android\")".parse().unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cfg {
+    /// A switch such as `unix`
+    Name(String),
+    /// A key-value pair such as `target_os = "linux"`
+    KeyValue(String, String),
+    /// `any(…)`: holds if any of the predicates hold
+    Any(Vec<Cfg>),
+    /// `all(…)`: holds if all of the predicates hold
+    All(Vec<Cfg>),
+    /// `not(…)`: holds if the predicate does not hold
+    Not(Box<Cfg>),
+}
+
+impl FromStr for Cfg {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Cfg, Error> {
+        let mut parser = Parser { tokens: try!(tokenize(s)), position: 0 };
+        let cfg = try!(parser.cfg());
+        if parser.position != parser.tokens.len() {
+            return Err(Error::InvalidCfg);
+        }
+        Ok(cfg)
+    }
+}
+
+impl fmt::Display for Cfg {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fn list(fmt: &mut fmt::Formatter, name: &str, cfgs: &[Cfg]) -> fmt::Result {
+            try!(write!(fmt, "{}(", name));
+            for (i, cfg) in cfgs.iter().enumerate() {
+                if i != 0 {
+                    try!(fmt.write_str(", "));
+                }
+                try!(write!(fmt, "{}", cfg));
+            }
+            fmt.write_str(")")
+        }
+        match *self {
+            Cfg::Name(ref name) => fmt.write_str(name),
+            Cfg::KeyValue(ref key, ref value) => write!(fmt, "{} = {:?}", key, value),
+            Cfg::Any(ref cfgs) => list(fmt, "any", cfgs),
+            Cfg::All(ref cfgs) => list(fmt, "all", cfgs),
+            Cfg::Not(ref cfg) => write!(fmt, "not({})", cfg),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    Equals,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '=' => tokens.push(Token::Equals),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => string.push(c),
+                            None => return Err(Error::InvalidCfg),
+                        },
+                        Some(c) => string.push(c),
+                        None => return Err(Error::InvalidCfg),
+                    }
+                }
+                tokens.push(Token::Str(string));
+            }
+            c if c.is_whitespace() => {}
+            c if is_ident_start(c) => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !is_ident_continue(c) {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            _ => return Err(Error::InvalidCfg),
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+/// Check whether the string is a valid `cfg` name
+pub fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().map(is_ident_start).unwrap_or(false) && chars.all(is_ident_continue)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), Error> {
+        if self.next() == Some(token) { Ok(()) } else { Err(Error::InvalidCfg) }
+    }
+
+    fn cfg(&mut self) -> Result<Cfg, Error> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            _ => return Err(Error::InvalidCfg),
+        };
+        match self.peek().cloned() {
+            Some(Token::Equals) => {
+                self.position += 1;
+                match self.next() {
+                    Some(Token::Str(value)) => Ok(Cfg::KeyValue(name, value)),
+                    _ => Err(Error::InvalidCfg),
+                }
+            }
+            Some(Token::LParen) => {
+                self.position += 1;
+                let cfgs = try!(self.list());
+                match &*name {
+                    "any" => Ok(Cfg::Any(cfgs)),
+                    "all" => Ok(Cfg::All(cfgs)),
+                    "not" if cfgs.len() == 1 => {
+                        Ok(Cfg::Not(Box::new(cfgs.into_iter().next().unwrap())))
+                    }
+                    _ => Err(Error::InvalidCfg),
+                }
+            }
+            _ => Ok(Cfg::Name(name)),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<Cfg>, Error> {
+        let mut cfgs = Vec::new();
+        loop {
+            if self.peek() == Some(&Token::RParen) {
+                self.position += 1;
+                return Ok(cfgs);
+            }
+            cfgs.push(try!(self.cfg()));
+            match self.peek() {
+                Some(&Token::Comma) => self.position += 1,
+                _ => return self.expect(Token::RParen).map(|_| cfgs),
+            }
+        }
+    }
+}
+
+impl TargetInfo {
+    /// Check if the `cfg` predicate holds for the target
+    ///
+    /// Corresponds to the `#[cfg({cfg})]` in Rust code.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let info = TargetInfo::new().expect("target info");
+    /// let cfg = "all(unix, not(target_os = \"macos\"))".parse().expect("valid cfg");
+    /// let is_unix_but_not_mac = info.target_cfg_matches(&cfg);
+    /// ```
+    pub fn target_cfg_matches(&self, cfg: &Cfg) -> bool {
+        match *cfg {
+            Cfg::Name(ref name) => self.target_cfg(name),
+            Cfg::KeyValue(ref key, ref value) => {
+                self.target_cfg_values(key).iter().any(|v| v == value)
+            }
+            Cfg::Any(ref cfgs) => cfgs.iter().any(|c| self.target_cfg_matches(c)),
+            Cfg::All(ref cfgs) => cfgs.iter().all(|c| self.target_cfg_matches(c)),
+            Cfg::Not(ref cfg) => !self.target_cfg_matches(cfg),
+        }
+    }
+}
+
+/// Declarative `cfg` aliases for build scripts
+///
+/// Maps custom `cfg` names to predicates, evaluates the predicates against the target and tells
+/// cargo to enable the aliases that hold.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use target_build_utils::{TargetInfo, CfgAliases};
+/// let target = TargetInfo::new().expect("target info");
+/// CfgAliases::new(&target)
+///     .alias("has_epoll", "any(target_os = \"linux\", target_os = \"android\")")
+///     .alias("wasm_browser", "all(target_arch = \"wasm32\", target_os = \"unknown\")")
+///     .emit()
+///     .expect("valid cfg aliases");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CfgAliases<'a> {
+    target: &'a TargetInfo,
+    aliases: Vec<(String, String)>,
+}
+
+impl<'a> CfgAliases<'a> {
+    /// Create an empty set of aliases for the target
+    pub fn new(target: &'a TargetInfo) -> CfgAliases<'a> {
+        CfgAliases {
+            target: target,
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Define the alias `name` to be enabled when `predicate` holds for the target
+    ///
+    /// The predicate uses the syntax of `#[cfg(…)]` attributes. Invalid names and predicates are
+    /// reported by `CfgAliases::enabled` and `CfgAliases::emit`.
+    pub fn alias(&mut self, name: &str, predicate: &str) -> &mut CfgAliases<'a> {
+        self.aliases.push((name.into(), predicate.into()));
+        self
+    }
+
+    /// Names of the aliases which hold for the target
+    pub fn enabled(&self) -> Result<Vec<&str>, Error> {
+        let mut enabled = Vec::new();
+        for &(ref name, ref predicate) in &self.aliases {
+            if !is_ident(name) {
+                return Err(Error::InvalidCfg);
+            }
+            let cfg: Cfg = try!(predicate.parse());
+            if self.target.target_cfg_matches(&cfg) {
+                enabled.push(&**name);
+            }
+        }
+        Ok(enabled)
+    }
+
+    /// Print the `cargo:rustc-cfg` directives for the aliases which hold for the target
+    ///
+    /// A `cargo:rustc-check-cfg` directive is printed for every alias, including those that do
+    /// not hold, so that `#[cfg]`s using them are not reported as unexpected. Nothing is printed
+    /// if any of the aliases is invalid.
+    pub fn emit(&self) -> Result<(), Error> {
+        let enabled = try!(self.enabled());
+        for &(ref name, _) in &self.aliases {
+            println!("cargo:rustc-check-cfg=cfg({})", name);
+        }
+        for name in enabled {
+            println!("cargo:rustc-cfg={}", name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cfg;
+    use {CfgAliases, TargetInfo};
+
+    #[test]
+    fn parse_cfg() {
+        let cfg: Cfg = "all(unix, not(target_os = \"macos\"), any(target_has_atomic = \"64\",))"
+            .parse().unwrap();
+        assert_eq!(cfg, Cfg::All(vec![
+            Cfg::Name("unix".into()),
+            Cfg::Not(Box::new(Cfg::KeyValue("target_os".into(), "macos".into()))),
+            Cfg::Any(vec![Cfg::KeyValue("target_has_atomic".into(), "64".into())]),
+        ]));
+        assert_eq!(cfg.to_string(),
+                   "all(unix, not(target_os = \"macos\"), any(target_has_atomic = \"64\"))");
+        for invalid in &["", "unix,", "not(unix, windows)", "foo(unix)", "target_os = linux",
+                         "target_os = \"linux", "any(unix", "unix)", "1unix"] {
+            assert!(invalid.parse::<Cfg>().is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            let mut aliases = CfgAliases::new(&ti);
+            aliases.alias("has_epoll", "any(target_os = \"linux\", target_os = \"android\")")
+                   .alias("wasm_browser", "all(target_arch = \"wasm32\", target_os = \"unknown\")")
+                   .alias("linux_64", "all(unix, target_pointer_width = \"64\")");
+            assert_eq!(aliases.enabled().unwrap(), ["has_epoll", "linux_64"]);
+            aliases.alias("not an ident", "unix");
+            aliases.enabled().err().unwrap();
+        }
+    }
+}
@@ -1,5 +1,81 @@
 //! Project Changelog
 
+/// Release 0.4.0 (unreleased)
+///
+/// # Breaking changes
+///
+/// * The `Error` enumeration gained a new variant `InvalidCfg` to signify an invalid `cfg`
+///   predicate, `OutDirUnset` to signify the `OUT_DIR` environment variable not being set, and
+///   `HostPkgConfig` to signify a refusal to use the host pkg-config when cross-compiling.
+/// * The `Error` enumeration gained new variants `InvalidCargoConfig` to signify an invalid cargo
+///   configuration file and `CargoConfigUnsupported` to signify the fact this particular build of
+///   the crate does not support reading cargo configuration files.
+/// * The `Error` enumeration gained a new variant `UnknownBinaryFormat` to signify a file not
+///   being an object file, library or executable of a known format.
+/// * The `target-pointer-width` of custom target JSON files must now be an integer, written
+///   either as a JSON number, as current rustc expects, or as a string.
+///
+/// # Other changes
+///
+/// * Custom target JSON files may inherit from a builtin target by naming it with the
+/// `base-target` key. The fully expanded specification is available via
+/// `TargetInfo::target_json`. The specification of the base target is taken from
+/// `rustc --print target-spec-json` when rustc allows unstable options, i.e. on nightly or with
+/// `RUSTC_BOOTSTRAP` set by the build.
+/// * Added `TargetInfo::from_json_str` and `TargetInfo::from_json_reader` to load custom target
+/// specifications which do not live in a file.
+/// * `TargetInfo::from_str` now searches for custom target JSON files in the same order rustc
+/// does, including `{sysroot}/lib/rustlib/{target}/target.json`.
+/// * Added `TargetInfo::target_json_path` and `TargetInfo::emit_rerun_directives`. The latter
+/// prints the `cargo:rerun-if-*` directives for the environment and files the target was
+/// resolved from.
+/// * Added `TargetInfo::target_cfg_values` to retrieve all values of multi-valued keys such as
+/// `target_feature`.
+/// * Added `Cfg`, a parsed `cfg` predicate, and `TargetInfo::target_cfg_matches` to evaluate it.
+/// * Added `CfgAliases` to declare custom `cfg`s enabled according to a predicate and emit them
+/// for cargo.
+/// * Added `TargetInfo::target_triple`.
+/// * Added `TargetInfo::rust_consts` and `TargetInfo::write_rust_consts` to generate Rust
+/// constants describing the target for use with `include!`. The type of every constant is the
+/// same for all targets.
+/// * Added `CHeader` to generate a C header with macros describing the target.
+/// * Added `TargetInfo::c_compiler_triple` and `TargetInfo::{c_compiler,ar,ranlib,strip}_tool`
+/// to find out the names of the C toolchain for the target.
+/// * Added `TargetInfo::cxx_compiler_tool` and `CMakeToolchain`, a generator of CMake toolchain
+/// files.
+/// * Added `MesonCrossFile`, a generator of Meson cross files.
+/// * Added `TargetInfo::autotools_host` and `TargetInfo::autotools_env` to configure autotools
+/// packages for the target.
+/// * Added `PkgConfig` to run pkg-config for the target rather than the host.
+/// * Added `TargetInfo::target_env_var` to look up target-scoped environment variables such as
+/// `CC_{target}` with the same precedence as the `cc` crate.
+/// * Added `TargetInfo::cargo_target_env_var` and friends to read the
+/// `CARGO_TARGET_{TRIPLE}_{KEY}` configuration of cargo.
+/// * Added `TargetInfo::cargo_config` to read the `linker`, `runner` and `rustflags` configured
+/// for the target in `.cargo/config.toml` files. This requires the new enabled-by-default `toml`
+/// optional dependency.
+/// * Added `TargetInfo::effective` and `TargetInfo::with_rustflags` to apply the `--cfg`,
+/// `-C target-feature` and `-C target-cpu` flags from `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS`
+/// to the target.
+/// * Added `TargetInfo::target_cpu`.
+/// * Added `TargetInfo::crt_static` to find out whether the C runtime is linked statically.
+/// * Added `TargetInfo::{dylib,staticlib}_{prefix,suffix}`, `TargetInfo::exe_suffix` and the
+/// `TargetInfo::{dylib,staticlib,exe,import_lib}_name` helpers to name the files produced for
+/// the target.
+/// * Added `TargetInfo::c_types` to find out the sizes and signedness of the C types of the
+/// target.
+/// * Added `TargetInfo::clang_target` and `TargetInfo::clang_args` to set up clang, e.g. for
+/// bindgen, for the target.
+/// * Added `TargetInfo::object_format` and `TargetInfo::debuginfo_format`.
+/// * Added `TargetInfo::elf_machine`, `TargetInfo::pe_machine` and `TargetInfo::macho_cpu` to
+/// identify the binaries built for the target.
+/// * Added `TargetInfo::verify_binary` and `TargetInfo::verify_binary_bytes` to check that an
+/// object file, library or executable was built for the target.
+/// * Added `TargetInfo::libc` and `TargetInfo::dynamic_linker`.
+/// * Added `TargetInfo::arm_info` to find out the instruction set architecture and the floating
+/// point ABI of ARM targets.
+pub mod r0_4_0 {}
+
 /// Release 0.3.0 (2017-02-10)
 ///
 /// # Breaking changes
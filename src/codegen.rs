@@ -0,0 +1,339 @@
+//! Generation of source code describing the target
+
+use std::env;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::Write as IoWrite;
+use std::path::PathBuf;
+
+use {Error, TargetInfo};
+
+/// Configuration keys which never have more than one value set at once
+///
+/// Any other key may have multiple values, including keys not known to this crate.
+const SINGLE_VALUED_KEYS: &'static [&'static str] = &[
+    "target_arch",
+    "target_os",
+    "target_env",
+    "target_endian",
+    "target_pointer_width",
+    "target_vendor",
+    "target_abi",
+];
+
+/// Configuration keys for which constants are always defined
+const ALWAYS_DEFINED_KEYS: &'static [&'static str] = &[
+    "target_vendor",
+    "target_abi",
+    "target_family",
+    "target_feature",
+    "target_has_atomic",
+];
+
+/// Widths `target_has_atomic` may take
+const ATOMIC_WIDTHS: &'static [&'static str] = &["8", "16", "32", "64", "128", "ptr"];
+
+/// C string literal with the contents of `s`
+///
+/// Bytes other than printable ASCII are written as octal escapes, which unlike hexadecimal
+/// escapes cannot run into the characters following them.
+fn c_string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for &b in s.as_bytes() {
+        match b {
+            b'"' | b'\\' => {
+                literal.push('\\');
+                literal.push(b as char);
+            }
+            // `??` starts a trigraph
+            b'?' if literal.ends_with('?') => literal.push_str("\\?"),
+            _ if (0x20..0x7f).contains(&b) => literal.push(b as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", b);
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Name of a constant or macro derived from a configuration key or value
+fn const_name(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+             .collect()
+}
+
+/// Write the generated file into `OUT_DIR`
+pub fn write_out_file(file_name: &str, contents: &str) -> Result<PathBuf, Error> {
+    let out_dir = try!(env::var_os("OUT_DIR").ok_or(Error::OutDirUnset));
+    let path = PathBuf::from(out_dir).join(file_name);
+    let mut file = try!(File::create(&path).map_err(Error::Io));
+    try!(file.write_all(contents.as_bytes()).map_err(Error::Io));
+    Ok(path)
+}
+
+impl TargetInfo {
+    /// All the keys with a value set for the target, in the order they were first seen
+    fn target_cfg_keys(&self) -> Vec<&str> {
+        let mut keys = vec!["target_arch", "target_os", "target_env", "target_endian",
+                            "target_pointer_width"];
+        for &(ref key, _) in self.other_keys.iter() {
+            if !keys.contains(&&**key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Rust source code defining constants which describe the target
+    ///
+    /// Constants are defined for the target triple, for every configuration key and for every
+    /// configuration switch set for the target:
+    ///
+    /// * `TARGET_TRIPLE: &str`;
+    /// * `TARGET_POINTER_WIDTH: u32`;
+    /// * `TARGET_{KEY}: &str` for keys which only ever have a single value: `TARGET_ARCH`,
+    ///   `TARGET_OS`, `TARGET_ENV`, `TARGET_ENDIAN`, `TARGET_VENDOR` and `TARGET_ABI`. The last
+    ///   two are always defined;
+    /// * `TARGET_{KEY}: &[&str]` for all the other keys, such as `TARGET_FEATURE`, regardless of
+    ///   the number of values set for the target. `TARGET_FAMILY`, `TARGET_FEATURE` and
+    ///   `TARGET_HAS_ATOMIC` are always defined;
+    /// * `TARGET_HAS_ATOMIC_{WIDTH}: bool` for all of the widths, such as `TARGET_HAS_ATOMIC_64`
+    ///   or `TARGET_HAS_ATOMIC_PTR`;
+    /// * `CFG_{SWITCH}: bool`, such as `CFG_UNIX`. `CFG_UNIX` and `CFG_WINDOWS` are always
+    ///   defined.
+    pub fn rust_consts(&self) -> String {
+        let mut out = String::new();
+        let w = &mut out;
+        let _ = writeln!(w, "// Generated by target_build_utils for target `{}`. Do not edit.\n",
+                         self.target_triple());
+        let _ = writeln!(w, "/// Target triple");
+        let _ = writeln!(w, "pub const TARGET_TRIPLE: &str = {:?};", self.target_triple());
+
+        let mut keys = self.target_cfg_keys();
+        for key in ALWAYS_DEFINED_KEYS {
+            if !keys.contains(key) {
+                keys.push(key);
+            }
+        }
+        for key in keys {
+            let values = self.target_cfg_values(key);
+            let _ = writeln!(w, "/// `cfg({})`", key);
+            if key == "target_pointer_width" {
+                let _ = writeln!(w, "pub const TARGET_POINTER_WIDTH: u32 = {};",
+                                 self.target_pointer_width());
+            } else if SINGLE_VALUED_KEYS.contains(&key) {
+                let _ = writeln!(w, "pub const {}: &str = {:?};", const_name(key),
+                                 values.first().cloned().unwrap_or(""));
+            } else {
+                let _ = writeln!(w, "pub const {}: &[&str] = &{:?};", const_name(key), values);
+            }
+        }
+
+        let atomics = self.target_cfg_values("target_has_atomic");
+        for width in ATOMIC_WIDTHS {
+            let _ = writeln!(w, "/// `cfg(target_has_atomic = {:?})`", width);
+            let _ = writeln!(w, "pub const TARGET_HAS_ATOMIC_{}: bool = {};", const_name(width),
+                             atomics.contains(width));
+        }
+
+        let mut switches = vec!["unix", "windows"];
+        for switch in self.switches.iter() {
+            if !switches.contains(&&**switch) {
+                switches.push(switch);
+            }
+        }
+        for switch in switches {
+            let _ = writeln!(w, "/// `cfg({})`", switch);
+            let _ = writeln!(w, "pub const CFG_{}: bool = {};", const_name(switch),
+                             self.target_cfg(switch));
+        }
+        out
+    }
+
+    /// Write `TargetInfo::rust_consts` into `target_consts.rs` in `OUT_DIR`
+    ///
+    /// Returns the path to the written file.
+    ///
+    /// # Example
+    ///
+    /// In `build.rs`:
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// target.write_rust_consts().expect("could not write target_consts.rs");
+    /// ```
+    ///
+    /// And then in the crate:
+    ///
+    /// ```rust,ignore
+    /// include!(concat!(env!("OUT_DIR"), "/target_consts.rs"));
+    /// ```
+    pub fn write_rust_consts(&self) -> Result<PathBuf, Error> {
+        write_out_file("target_consts.rs", &self.rust_consts())
+    }
+}
+
+/// Generator of a C header with macros describing the target
+///
+/// For every configuration key and value set for the target a `{PREFIX}{KEY}_{VALUE}` macro is
+/// defined to `1`, e.g. `RUST_TARGET_ARCH_X86_64` or `RUST_TARGET_FEATURE_SSE2`. Keys which
+/// only ever have a single value also get a `{PREFIX}{KEY}` macro defined to the value as a
+/// string literal, e.g. `RUST_TARGET_OS "linux"`, except for `{PREFIX}TARGET_POINTER_WIDTH`
+/// which is an integer. Configuration switches get a `{PREFIX}{SWITCH}` macro, e.g. `RUST_UNIX`.
+/// `{PREFIX}TARGET_TRIPLE` is defined to the target triple.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use target_build_utils::{TargetInfo, CHeader};
+/// let target = TargetInfo::new().expect("could not get target");
+/// CHeader::new(&target)
+///     .prefix("MYLIB_")
+///     .write("target.h")
+///     .expect("could not write target.h");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CHeader<'a> {
+    target: &'a TargetInfo,
+    prefix: String,
+    // `None` if the default include guard should be used
+    guard: Option<Option<String>>,
+}
+
+impl<'a> CHeader<'a> {
+    /// Create a header generator for the target
+    ///
+    /// The macro prefix defaults to `RUST_` and the include guard defaults to
+    /// `{PREFIX}TARGET_H`.
+    pub fn new(target: &'a TargetInfo) -> CHeader<'a> {
+        CHeader {
+            target: target,
+            prefix: String::from("RUST_"),
+            guard: None,
+        }
+    }
+
+    /// Set the prefix of all the defined macros
+    pub fn prefix(&mut self, prefix: &str) -> &mut CHeader<'a> {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set the name of the include guard macro, or disable the include guard with `None`
+    pub fn include_guard(&mut self, guard: Option<&str>) -> &mut CHeader<'a> {
+        self.guard = Some(guard.map(String::from));
+        self
+    }
+
+    /// Contents of the header
+    pub fn generate(&self) -> String {
+        let target = self.target;
+        let prefix = &*self.prefix;
+        let guard = match self.guard {
+            Some(ref guard) => guard.clone(),
+            None => Some(format!("{}TARGET_H", prefix)),
+        };
+
+        let mut out = String::new();
+        let w = &mut out;
+        let _ = writeln!(w, "/* Generated by target_build_utils for target `{}`. Do not edit. */",
+                         target.target_triple());
+        if let Some(ref guard) = guard {
+            let _ = writeln!(w, "#ifndef {}\n#define {}", guard, guard);
+        }
+        let _ = writeln!(w, "\n#define {}TARGET_TRIPLE {}", prefix,
+                         c_string_literal(target.target_triple()));
+        for key in target.target_cfg_keys() {
+            let values = target.target_cfg_values(key);
+            if key == "target_pointer_width" {
+                let _ = writeln!(w, "#define {}TARGET_POINTER_WIDTH {}", prefix,
+                                 target.target_pointer_width());
+            } else if values.len() == 1 && SINGLE_VALUED_KEYS.contains(&key) {
+                let _ = writeln!(w, "#define {}{} {}", prefix, const_name(key),
+                                 c_string_literal(values[0]));
+            }
+            for value in values {
+                if !value.is_empty() {
+                    let _ = writeln!(w, "#define {}{}_{} 1", prefix, const_name(key),
+                                     const_name(value));
+                }
+            }
+        }
+        for switch in target.switches.iter() {
+            let _ = writeln!(w, "#define {}{} 1", prefix, const_name(switch));
+        }
+        if guard.is_some() {
+            let _ = writeln!(w, "\n#endif");
+        }
+        out
+    }
+
+    /// Write the header into `OUT_DIR`
+    ///
+    /// Returns the path to the written file.
+    pub fn write(&self, file_name: &str) -> Result<PathBuf, Error> {
+        write_out_file(file_name, &self.generate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::c_string_literal;
+    use {CHeader, TargetInfo};
+
+    #[test]
+    fn rust_consts() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            let consts = ti.rust_consts();
+            for line in &["pub const TARGET_TRIPLE: &str = \"x86_64-unknown-linux-gnu\";",
+                          "pub const TARGET_ARCH: &str = \"x86_64\";",
+                          "pub const TARGET_POINTER_WIDTH: u32 = 64;",
+                          "pub const TARGET_HAS_ATOMIC_64: bool = true;",
+                          "pub const TARGET_HAS_ATOMIC_128: bool = false;",
+                          "pub const CFG_UNIX: bool = true;",
+                          "pub const CFG_WINDOWS: bool = false;"] {
+                assert!(consts.lines().any(|l| l == *line), "{} missing in:\n{}", line, consts);
+            }
+            assert!(consts.contains("pub const TARGET_FEATURE: &[&str] = &["));
+            assert!(consts.contains("pub const TARGET_VENDOR: &str = "));
+            // Keys not known to be single-valued are slices even with a single value
+            assert!(consts.lines().any(|l| l == "pub const PANIC: &[&str] = &[\"unwind\"];"));
+            assert!(consts.lines().any(|l| l == "pub const TARGET_FAMILY: &[&str] = &[\"unix\"];"));
+        }
+    }
+
+    #[test]
+    fn c_header() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            let header = CHeader::new(&ti).generate();
+            for line in &["#ifndef RUST_TARGET_H",
+                          "#define RUST_TARGET_TRIPLE \"x86_64-unknown-linux-gnu\"",
+                          "#define RUST_TARGET_ARCH \"x86_64\"",
+                          "#define RUST_TARGET_ARCH_X86_64 1",
+                          "#define RUST_TARGET_OS_LINUX 1",
+                          "#define RUST_TARGET_ENDIAN_LITTLE 1",
+                          "#define RUST_TARGET_POINTER_WIDTH 64",
+                          "#define RUST_UNIX 1",
+                          "#endif"] {
+                assert!(header.lines().any(|l| l == *line), "{} missing in:\n{}", line, header);
+            }
+            assert!(!header.contains("#define RUST_TARGET_FEATURE \""));
+
+            let header = CHeader::new(&ti).prefix("FOO_").include_guard(None).generate();
+            assert!(header.contains("#define FOO_TARGET_OS \"linux\"\n"));
+            assert!(!header.contains("#ifndef"));
+            let header = CHeader::new(&ti).include_guard(Some("BAR_H")).generate();
+            assert!(header.contains("#ifndef BAR_H\n#define BAR_H\n"));
+        }
+    }
+
+    #[test]
+    fn c_string_literals() {
+        assert_eq!(c_string_literal("linux"), r#""linux""#);
+        assert_eq!(c_string_literal("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(c_string_literal("\u{e9}1\n"), r#""\303\2511\012""#);
+        assert_eq!(c_string_literal("what??!"), r#""what?\?!""#);
+    }
+}
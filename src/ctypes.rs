@@ -0,0 +1,165 @@
+//! Sizes and signedness of the C types of the target
+
+use TargetInfo;
+
+/// Format of the C `long double` type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongDouble {
+    /// Same as `double`, the IEEE 754 binary64 format
+    F64,
+    /// The x87 80-bit extended precision format, padded to 96 or 128 bits in memory
+    X87,
+    /// The IEEE 754 binary128 format
+    F128,
+    /// IBM double-double, a pair of `double`s, as used on PowerPC
+    DoubleDouble,
+}
+
+/// Sizes and signedness of the C types of the target
+///
+/// The widths are in bits. This covers the common data models:
+///
+/// * ILP32, where `int`, `long` and pointers are 32 bits wide, used by 32-bit targets;
+/// * LP64, where `long` and pointers are 64 bits wide, used by 64-bit Unix-like targets;
+/// * LLP64, where `long` is 32 bits wide even though pointers are 64 bits wide, used by 64-bit
+///   Windows.
+///
+/// See `TargetInfo::c_types`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CTypes {
+    /// Whether `char` is signed
+    pub char_signed: bool,
+    /// Width of `short`
+    pub short_width: u32,
+    /// Width of `int`
+    pub int_width: u32,
+    /// Width of `long`
+    pub long_width: u32,
+    /// Width of `long long`
+    pub long_long_width: u32,
+    /// Width of pointers and `size_t`
+    pub pointer_width: u32,
+    /// Width of `wchar_t`
+    pub wchar_width: u32,
+    /// Whether `wchar_t` is signed
+    pub wchar_signed: bool,
+    /// Format of `long double`
+    pub long_double: LongDouble,
+}
+
+impl TargetInfo {
+    /// Sizes and signedness of the C types of the target
+    ///
+    /// These are derived from the architecture, OS, environment and pointer width of the target.
+    /// The width of `int` is taken from the `target-c-int-width` field of custom JSON targets,
+    /// if present.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("aarch64-unknown-linux-gnu").expect("target info");
+    /// let c_types = target.c_types();
+    /// assert!(!c_types.char_signed);
+    /// assert_eq!(c_types.long_width, 64);
+    /// ```
+    pub fn c_types(&self) -> CTypes {
+        let arch = self.target_arch();
+        let windows = self.is_like_windows();
+        let apple = self.is_like_osx();
+        let pointer_width = self.target_pointer_width().parse().unwrap_or(32);
+        let sixteen_bit = arch == "avr" || arch == "msp430";
+
+        // Same as the definition of `c_char` in Rust
+        let unsigned_char_arch = match arch {
+            "aarch64" | "arm" | "csky" | "hexagon" | "msp430" | "powerpc" | "powerpc64" |
+            "riscv32" | "riscv64" | "s390x" | "xtensa" => true,
+            _ => false,
+        };
+        let int_width = self.spec_value("target-c-int-width").and_then(|w| w.parse().ok())
+                            .unwrap_or(if sixteen_bit { 16 } else { 32 });
+        // Cygwin uses LP64 rather than the LLP64 of Windows
+        let llp64 = windows && self.target_os() != "cygwin";
+        let long_width = if pointer_width == 64 && !llp64 { 64 } else { 32 };
+        let (wchar_width, wchar_signed) = if windows {
+            (16, false)
+        } else if sixteen_bit {
+            (16, true)
+        } else {
+            (32, apple || (arch != "arm" && arch != "aarch64"))
+        };
+        let long_double = match arch {
+            _ if self.is_like_msvc() => LongDouble::F64,
+            "x86" if self.target_os() == "android" => LongDouble::F64,
+            "x86_64" if self.target_os() == "android" => LongDouble::F128,
+            "x86" | "x86_64" => LongDouble::X87,
+            "aarch64" if apple || windows => LongDouble::F64,
+            "powerpc" | "powerpc64" if self.target_env() == "gnu" => LongDouble::DoubleDouble,
+            "aarch64" | "riscv32" | "riscv64" | "s390x" | "mips64" | "sparc" | "sparc64" |
+            "loongarch64" | "wasm32" | "wasm64" => LongDouble::F128,
+            _ => LongDouble::F64,
+        };
+        CTypes {
+            char_signed: !unsigned_char_arch || apple || windows,
+            short_width: 16,
+            int_width: int_width,
+            long_width: long_width,
+            long_long_width: 64,
+            pointer_width: pointer_width,
+            wchar_width: wchar_width,
+            wchar_signed: wchar_signed,
+            long_double: long_double,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {LongDouble, TargetInfo};
+
+    #[test]
+    fn c_types() {
+        macro_rules! check_types {
+            ($($str: expr => ($char_signed: expr, $long: expr, $pointer: expr, $wchar: expr,
+                              $long_double: expr)),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        let c_types = ti.c_types();
+                        assert_eq!(c_types.char_signed, $char_signed, "{}", $str);
+                        assert_eq!(c_types.int_width, 32, "{}", $str);
+                        assert_eq!(c_types.long_width, $long, "{}", $str);
+                        assert_eq!(c_types.pointer_width, $pointer, "{}", $str);
+                        assert_eq!(c_types.wchar_width, $wchar, "{}", $str);
+                        assert_eq!(c_types.long_double, $long_double, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_types!("x86_64-unknown-linux-gnu" => (true, 64, 64, 32, LongDouble::X87),
+                     "x86_64-pc-windows-msvc" => (true, 32, 64, 16, LongDouble::F64),
+                     "x86_64-pc-windows-gnu" => (true, 32, 64, 16, LongDouble::X87),
+                     "x86_64-pc-cygwin" => (true, 64, 64, 16, LongDouble::X87),
+                     "i686-unknown-linux-gnu" => (true, 32, 32, 32, LongDouble::X87),
+                     "aarch64-unknown-linux-gnu" => (false, 64, 64, 32, LongDouble::F128),
+                     "aarch64-apple-darwin" => (true, 64, 64, 32, LongDouble::F64),
+                     "armv7-unknown-linux-gnueabihf" => (false, 32, 32, 32, LongDouble::F64),
+                     "powerpc64le-unknown-linux-gnu" =>
+                         (false, 64, 64, 32, LongDouble::DoubleDouble),
+                     "riscv64gc-unknown-linux-gnu" => (false, 64, 64, 32, LongDouble::F128));
+        if let Ok(ti) = TargetInfo::from_str("avr-none") {
+            assert_eq!(ti.c_types().int_width, 16);
+            assert_eq!(ti.c_types().long_width, 32);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn c_types_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "base-target": "thumbv7em-none-eabihf",
+            "target-c-int-width": "16"
+        }"#).unwrap();
+        assert_eq!(ti.c_types().int_width, 16);
+        assert!(!ti.c_types().char_signed);
+    }
+}
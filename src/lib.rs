@@ -49,15 +49,44 @@
 //! target system when your crate is being cross-compiled.
 #[cfg(feature = "serde_json")]
 extern crate serde_json;
+#[cfg(feature = "toml")]
+extern crate toml;
 extern crate phf;
 
 use std::env;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::process::Command;
+use std::sync::{Mutex, Once};
 use std::borrow::Cow;
 use std::borrow::Cow::Borrowed as B;
 
 pub mod changelog;
+mod arm;
+mod artifacts;
+mod cargo;
+mod cargo_config;
+mod cfg;
+mod codegen;
+mod ctypes;
+mod libc;
+mod object;
+mod pkg_config;
+mod rustflags;
+mod toolchain;
+mod verify;
+
+pub use arm::{ArmInfo, ArmIsa, FloatAbi};
+pub use cargo_config::CargoTargetConfig;
+pub use cfg::{Cfg, CfgAliases};
+pub use codegen::CHeader;
+pub use ctypes::{CTypes, LongDouble};
+pub use libc::Libc;
+pub use object::{DebuginfoFormat, ElfMachine, MachOCpu, ObjectFormat};
+pub use pkg_config::PkgConfig;
+pub use toolchain::{CMakeToolchain, MesonCrossFile};
+pub use verify::Mismatch;
 
 #[derive(Debug)]
 pub enum Error {
@@ -71,6 +100,18 @@ pub enum Error {
     Io(::std::io::Error),
     /// Crate was built without support for custom targets JSON file
     CustomTargetsUnsupported,
+    /// A `cfg` predicate or name was not valid
+    InvalidCfg,
+    /// The `OUT_DIR` environment variable does not exist
+    OutDirUnset,
+    /// Refused to use the host pkg-config to find libraries for a different target
+    HostPkgConfig,
+    /// Cargo configuration file was not valid
+    InvalidCargoConfig,
+    /// Crate was built without support for reading cargo configuration files
+    CargoConfigUnsupported,
+    /// The file is not an object file, library or executable of a known format
+    UnknownBinaryFormat,
 }
 
 impl ::std::fmt::Display for Error {
@@ -90,6 +131,12 @@ impl ::std::error::Error for Error {
             Error::InvalidSpec => "Custom target JSON file was not valid",
             Error::Io(ref e) => e.description(),
             Error::CustomTargetsUnsupported => "Support for custom target JSON file was disabled at compilation",
+            Error::InvalidCfg => "The cfg predicate was not valid",
+            Error::OutDirUnset => "OUT_DIR environment variable is not set",
+            Error::HostPkgConfig => "Refused to use the host pkg-config when cross-compiling",
+            Error::InvalidCargoConfig => "Cargo configuration file was not valid",
+            Error::CargoConfigUnsupported => "Support for cargo configuration files was disabled at compilation",
+            Error::UnknownBinaryFormat => "The file is not an object file, library or executable of a known format",
         }
     }
 
@@ -104,8 +151,16 @@ impl ::std::error::Error for Error {
 
 include!(concat!(env!("OUT_DIR"), "/builtins.rs"));
 
+/// Parsed custom target JSON specification
+#[cfg(feature = "serde_json")]
+type Spec = serde_json::Map<String, serde_json::Value>;
+#[cfg(not(feature = "serde_json"))]
+type Spec = ();
+
 #[derive(Clone, Debug)]
 pub struct TargetInfo {
+    // Target triple or the name of the custom target
+    name: Cow<'static, str>,
     arch: Cow<'static, str>,
     os: Cow<'static, str>,
     env: Cow<'static, str>,
@@ -115,6 +170,14 @@ pub struct TargetInfo {
     switches: Cow<'static, [Cow<'static, str>]>,
     // Other keys such as `target_vendor` or `target_has_atomic`
     other_keys: Cow<'static, [(Cow<'static, str>, Cow<'static, str>)]>,
+    // Expanded JSON specification for custom targets
+    json: Option<String>,
+    // The same specification, parsed
+    spec: Option<Spec>,
+    // Path to the custom target JSON file this target was loaded from
+    path: Option<PathBuf>,
+    // CPU the code is generated for, if not the default of the target
+    cpu: Option<String>,
 }
 
 impl TargetInfo {
@@ -135,7 +198,16 @@ impl TargetInfo {
 
     /// Calculate the target info from the provided target value
     ///
-    /// String may contain a triple or path to the json file.
+    /// String may contain a triple or path to the json file. JSON files may name a builtin target
+    /// to inherit from with the [`base-target`](constant.BASE_TARGET_KEY.html) key.
+    ///
+    /// Custom targets are searched for in the same order rustc does:
+    ///
+    /// 1. the value is interpreted as a path to the JSON file, relative to the current
+    ///    directory, which cargo sets to the package directory when running build scripts;
+    /// 2. `{value}.json` in each of the directories listed in `RUST_TARGET_PATH`;
+    /// 3. `{sysroot}/lib/rustlib/{value}/target.json`, where the sysroot is reported by
+    ///    `rustc --print sysroot` (`RUSTC` environment variable is respected).
     ///
     /// # Example
     ///
@@ -145,61 +217,367 @@ impl TargetInfo {
     ///     .expect("could not get target");
     /// ```
     pub fn from_str(s: &str) -> Result<TargetInfo, Error> {
-        #[cfg(feature = "serde_json")]
-        fn load_json(path: &Path) -> Result<TargetInfo, Error> {
-            use std::fs::File;
-            use serde_json as s;
-            let f = try!(File::open(path).map_err(|e| Error::Io(e)));
-            let json: s::Value = try!(s::from_reader(f).map_err(|_| Error::InvalidSpec));
-            let req = |name: &str|
-                json.get(name).and_then(|a| a.as_str()).ok_or(Error::InvalidSpec);
-
-            let vendor = json.get("vendor").and_then(|s| s.as_str()).unwrap_or("unknown").into();
-            Ok(TargetInfo {
-                arch: Cow::Owned(try!(req("arch")).into()),
-                os: Cow::Owned(try!(req("os")).into()),
-                env: Cow::Owned(json.get("env").and_then(|s| s.as_str()).unwrap_or("").into()),
-                endian: Cow::Owned(try!(req("target-endian")).into()),
-                pointer_width: Cow::Owned(try!(req("target-pointer-width")).into()),
-                switches: B(&[]),
-                other_keys: Cow::Owned(vec![(B("target_vendor"), Cow::Owned(vendor))]),
-            })
+        if let Some(t) = TargetInfo::load_specific(s) {
+            return Ok(t);
         }
-
-        #[cfg(not(feature = "serde_json"))]
-        fn load_json(_: &Path) -> Result<TargetInfo, Error> {
-            Err(Error::CustomTargetsUnsupported)
+        match find_target_json(s) {
+            Some(path) => load_json(&path),
+            None => Err(Error::TargetNotFound),
         }
+    }
 
-        if let Some(t) = TargetInfo::load_specific(s) {
-            return Ok(t);
+    /// Calculate the target info from a custom target JSON specification
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_json_str(include_str!("my-great-target.json"))
+    ///     .expect("could not get target");
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn from_json_str(s: &str) -> Result<TargetInfo, Error> {
+        from_json(try!(serde_json::from_str(s).map_err(|_| Error::InvalidSpec)))
+    }
+
+    /// Calculate the target info from a custom target JSON specification
+    #[cfg(not(feature = "serde_json"))]
+    pub fn from_json_str(_: &str) -> Result<TargetInfo, Error> {
+        Err(Error::CustomTargetsUnsupported)
+    }
+
+    /// Calculate the target info from a reader producing a custom target JSON specification
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let spec: &[u8] = include_bytes!("my-great-target.json");
+    /// let target = TargetInfo::from_json_reader(spec).expect("could not get target");
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn from_json_reader<R: Read>(r: R) -> Result<TargetInfo, Error> {
+        from_json(try!(serde_json::from_reader(r).map_err(|_| Error::InvalidSpec)))
+    }
+
+    /// Calculate the target info from a reader producing a custom target JSON specification
+    #[cfg(not(feature = "serde_json"))]
+    pub fn from_json_reader<R: Read>(_: R) -> Result<TargetInfo, Error> {
+        Err(Error::CustomTargetsUnsupported)
+    }
+
+    fn load_specific(s: &str) -> Option<TargetInfo> {
+        BUILTINS.get(s).cloned()
+    }
+
+    /// Fully expanded custom target JSON specification
+    ///
+    /// Returns `None` for builtin targets. For custom JSON targets which inherit from a builtin
+    /// target via the `base-target` key, the returned specification has the base target merged
+    /// in. The specification of the base target is taken from `rustc --print target-spec-json`,
+    /// in which case the result can be handed over to the same rustc. That option is unstable
+    /// and is only available with a nightly rustc or with `RUSTC_BOOTSTRAP` set in the
+    /// environment; this crate never sets `RUSTC_BOOTSTRAP` itself. Otherwise only the fields
+    /// derived from the `cfg` values of the base target are merged in, which is not enough for
+    /// rustc to load the target.
+    pub fn target_json(&self) -> Option<&str> {
+        self.json.as_ref().map(|s| &**s)
+    }
+
+    /// Path to the custom target JSON file this target was loaded from
+    ///
+    /// Returns `None` for builtin targets and targets loaded with `TargetInfo::from_json_str` or
+    /// `TargetInfo::from_json_reader`.
+    pub fn target_json_path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|p| &**p)
+    }
+
+    /// Value of a scalar field of the custom target JSON specification
+    ///
+    /// Booleans and numbers are converted to strings.
+    #[cfg(feature = "serde_json")]
+    fn spec_value(&self, key: &str) -> Option<String> {
+        use serde_json::Value;
+        let spec = match self.spec {
+            Some(ref spec) => spec,
+            None => return None,
+        };
+        match spec.get(key) {
+            Some(&Value::String(ref s)) => Some(s.clone()),
+            Some(&Value::Bool(b)) => Some(b.to_string()),
+            Some(&Value::Number(ref n)) => Some(n.to_string()),
+            _ => None,
         }
-        let path = Path::new(s);
-        if path.is_file() {
-            return load_json(&path);
+    }
+
+    #[cfg(not(feature = "serde_json"))]
+    fn spec_value(&self, _: &str) -> Option<String> {
+        None
+    }
+
+    /// Triple of the target as understood by LLVM
+    ///
+    /// This is the `llvm-target` of custom JSON targets and the target triple otherwise.
+    fn llvm_triple<'a>(&'a self) -> Cow<'a, str> {
+        match self.spec_value("llvm-target") {
+            Some(t) => Cow::Owned(t),
+            None => Cow::Borrowed(self.target_triple()),
         }
-        let path = {
-            let mut target = String::from(s);
-            target.push_str(".json");
-            PathBuf::from(target)
+    }
+
+    /// Value of a boolean field of the custom target JSON specification, or the default
+    fn spec_flag(&self, key: &str, default: bool) -> bool {
+        self.spec_value(key).map(|v| v == "true").unwrap_or(default)
+    }
+
+    /// Whether the target is Windows-like, as per the `is-like-windows` specification field
+    fn is_like_windows(&self) -> bool {
+        let default = match self.target_os() {
+            "windows" | "uefi" | "cygwin" => true,
+            _ => false,
         };
-        let target_path = env::var_os("RUST_TARGET_PATH")
-                              .unwrap_or(OsString::new());
-        for dir in env::split_paths(&target_path) {
-            let p =  dir.join(&path);
-            if p.is_file() {
-                return load_json(&p);
+        self.spec_flag("is-like-windows", default)
+    }
+
+    /// Whether the target uses the MSVC toolchain, as per the `is-like-msvc` specification field
+    fn is_like_msvc(&self) -> bool {
+        let default = self.target_env() == "msvc" || self.target_os() == "uefi";
+        self.spec_flag("is-like-msvc", default)
+    }
+
+    /// Whether the target is an Apple platform, as per the `is-like-darwin` specification field,
+    /// or `is-like-osx` as it was called by older rustc
+    fn is_like_osx(&self) -> bool {
+        let default = match self.target_os() {
+            "macos" | "ios" | "tvos" | "watchos" | "visionos" => true,
+            _ => false,
+        };
+        let default = self.spec_flag("is-like-osx", default);
+        self.spec_flag("is-like-darwin", default)
+    }
+}
+
+/// Find the custom target JSON file for the target the way rustc does
+fn find_target_json(s: &str) -> Option<PathBuf> {
+    let target_path = env::var_os("RUST_TARGET_PATH").unwrap_or(OsString::new());
+    find_target_json_from(s, &target_path, rustc_sysroot)
+}
+
+/// Find the custom target JSON file in the current directory, `target_path` and the sysroot
+///
+/// The sysroot is only queried if the file was not found elsewhere.
+fn find_target_json_from<F>(s: &str, target_path: &OsStr, sysroot: F) -> Option<PathBuf>
+where F: FnOnce() -> Option<PathBuf> {
+    // Relative to the current directory, which is the package directory in build scripts
+    let path = Path::new(s);
+    if path.is_file() {
+        return Some(path.canonicalize().unwrap_or(path.to_path_buf()));
+    }
+
+    let file_name = {
+        let mut target = String::from(s);
+        target.push_str(".json");
+        PathBuf::from(target)
+    };
+    for dir in env::split_paths(target_path) {
+        let p =  dir.join(&file_name);
+        if p.is_file() {
+            return Some(p.canonicalize().unwrap_or(p));
+        }
+    }
+
+    if let Some(sysroot) = sysroot() {
+        let p = sysroot.join("lib").join("rustlib").join(s).join("target.json");
+        if p.is_file() {
+            return Some(p);
+        }
+    }
+    None
+}
+
+/// Sysroot of the rustc specified by the `RUSTC` environment variable
+///
+/// The sysroot is cached for as long as the `RUSTC` environment variable does not change.
+fn rustc_sysroot() -> Option<PathBuf> {
+    type Cache = Mutex<Option<(OsString, Option<PathBuf>)>>;
+    static INIT: Once = Once::new();
+    static mut SYSROOT: *const Cache = ::std::ptr::null();
+    let cache: &Cache = unsafe {
+        INIT.call_once(|| SYSROOT = Box::into_raw(Box::new(Mutex::new(None))));
+        &*SYSROOT
+    };
+    let rustc = env::var_os("RUSTC").unwrap_or(OsString::from("rustc"));
+    let mut cache = match cache.lock() {
+        Ok(cache) => cache,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if let Some((ref cached_rustc, ref sysroot)) = *cache {
+        if *cached_rustc == rustc {
+            return sysroot.clone();
+        }
+    }
+    let sysroot = match Command::new(&rustc).arg("--print=sysroot").output() {
+        Ok(ref o) if o.status.success() => {
+            String::from_utf8(o.stdout.clone()).ok().map(|s| PathBuf::from(s.trim()))
+        }
+        _ => None,
+    };
+    *cache = Some((rustc, sysroot.clone()));
+    sysroot
+}
+
+/// Key used by custom target JSON files to name the builtin target they inherit from
+///
+/// This is an extension specific to this crate. Only the fields which are not specified by the
+/// JSON file itself are taken from the base target. The key is removed from the expanded
+/// specification returned by `TargetInfo::target_json`.
+///
+/// ```json
+/// {
+///     "base-target": "thumbv7em-none-eabihf",
+///     "os": "myos"
+/// }
+/// ```
+pub const BASE_TARGET_KEY: &'static str = "base-target";
+
+#[cfg(feature = "serde_json")]
+fn load_json(path: &Path) -> Result<TargetInfo, Error> {
+    use std::fs::File;
+    let f = try!(File::open(path).map_err(Error::Io));
+    let mut target = try!(TargetInfo::from_json_reader(f));
+    if let Some(stem) = path.file_stem() {
+        target.name = Cow::Owned(stem.to_string_lossy().into_owned());
+    }
+    target.path = Some(path.to_path_buf());
+    Ok(target)
+}
+
+#[cfg(not(feature = "serde_json"))]
+fn load_json(_: &Path) -> Result<TargetInfo, Error> {
+    Err(Error::CustomTargetsUnsupported)
+}
+
+#[cfg(feature = "serde_json")]
+fn from_json(json: serde_json::Value) -> Result<TargetInfo, Error> {
+    use serde_json::Value;
+    let mut spec = match json {
+        Value::Object(o) => o,
+        _ => return Err(Error::InvalidSpec),
+    };
+    let base = match spec.remove(BASE_TARGET_KEY) {
+        Some(Value::String(name)) => {
+            let base = try!(TargetInfo::load_specific(&name).ok_or(Error::TargetNotFound));
+            let mut expanded = base_spec(&name, &base);
+            for (k, v) in spec {
+                expanded.insert(k, v);
             }
+            spec = expanded;
+            Some(base)
+        }
+        Some(_) => return Err(Error::InvalidSpec),
+        None => None,
+    };
+
+    let (name, arch, os, env, endian, pointer_width, vendor) = {
+        let req = |name: &str|
+            spec.get(name).and_then(|a| a.as_str()).ok_or(Error::InvalidSpec);
+        (spec.get("llvm-target").and_then(|s| s.as_str()).unwrap_or("").to_owned(),
+         try!(req("arch")).to_owned(),
+         try!(req("os")).to_owned(),
+         spec.get("env").and_then(|s| s.as_str()).unwrap_or("").to_owned(),
+         try!(req("target-endian")).to_owned(),
+         try!(spec.get("target-pointer-width").and_then(|w| match *w {
+             Value::String(ref w) if w.parse::<u32>().is_ok() => Some(w.clone()),
+             Value::Number(ref w) if w.is_u64() => Some(w.to_string()),
+             _ => None,
+         }).ok_or(Error::InvalidSpec)),
+         spec.get("vendor").and_then(|s| s.as_str()).unwrap_or("unknown").to_owned())
+    };
+    let cpu = spec.get("cpu").and_then(|s| s.as_str()).map(String::from);
+    let json = try!(serde_json::to_string_pretty(&spec).map_err(|_| Error::InvalidSpec));
+
+    let (switches, mut other_keys) = match base {
+        Some(base) => (base.switches, base.other_keys.into_owned()),
+        None => (B(&[][..]), Vec::new()),
+    };
+    other_keys.retain(|&(ref k, _)| k != "target_vendor");
+    other_keys.push((B("target_vendor"), Cow::Owned(vendor)));
+    Ok(TargetInfo {
+        name: Cow::Owned(name),
+        arch: Cow::Owned(arch),
+        os: Cow::Owned(os),
+        env: Cow::Owned(env),
+        endian: Cow::Owned(endian),
+        pointer_width: Cow::Owned(pointer_width),
+        switches: switches,
+        other_keys: Cow::Owned(other_keys),
+        json: Some(json),
+        spec: Some(spec),
+        path: None,
+        cpu: cpu,
+    })
+}
+
+/// Build the JSON specification of a builtin target
+///
+/// The specification printed by `rustc --print target-spec-json` is used when the rustc
+/// specified by the `RUSTC` environment variable can print it. The fields which can be derived
+/// from the `cfg` values of the target are always present, as rustc omits the fields which have
+/// their default values.
+#[cfg(feature = "serde_json")]
+fn base_spec(name: &str, base: &TargetInfo)
+-> serde_json::Map<String, serde_json::Value> {
+    let mut spec = serde_json::Map::new();
+    spec.insert("llvm-target".into(), name.into());
+    spec.insert("arch".into(), base.target_arch().into());
+    spec.insert("os".into(), base.target_os().into());
+    if !base.target_env().is_empty() {
+        spec.insert("env".into(), base.target_env().into());
+    }
+    if let Some(vendor) = base.target_vendor() {
+        spec.insert("vendor".into(), vendor.into());
+    }
+    spec.insert("target-endian".into(), base.target_endian().into());
+    let pointer_width = base.target_pointer_width().parse::<u64>().unwrap_or(0);
+    spec.insert("target-pointer-width".into(), pointer_width.into());
+    if let Some(rustc_spec) = rustc_target_spec(name) {
+        for (k, v) in rustc_spec {
+            spec.insert(k, v);
         }
-        Err(Error::TargetNotFound)
     }
+    spec
+}
 
-    fn load_specific(s: &str) -> Option<TargetInfo> {
-        BUILTINS.get(s).cloned()
+/// Specification of a builtin target as printed by the rustc specified by the `RUSTC`
+/// environment variable
+///
+/// `--print target-spec-json` is an unstable option. `RUSTC_BOOTSTRAP` is not set here, so this
+/// only succeeds with a nightly rustc or when the build itself opted in by setting
+/// `RUSTC_BOOTSTRAP`. `None` is returned otherwise.
+#[cfg(feature = "serde_json")]
+fn rustc_target_spec(name: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let rustc = env::var_os("RUSTC").unwrap_or(OsString::from("rustc"));
+    let output = match Command::new(rustc)
+                     .args(&["-Zunstable-options", "--print=target-spec-json", "--target", name])
+                     .output() {
+        Ok(ref o) if o.status.success() => o.stdout.clone(),
+        _ => return None,
+    };
+    match serde_json::from_slice(&output) {
+        Ok(serde_json::Value::Object(spec)) => Some(spec),
+        _ => None,
     }
 }
 
 impl TargetInfo {
+    /// Target triple
+    ///
+    /// For custom JSON targets loaded from a file this is the file name without the `.json`
+    /// extension, just like rustc does. Targets loaded with `TargetInfo::from_json_str` or
+    /// `TargetInfo::from_json_reader` use the `llvm-target` value instead, if present.
+    pub fn target_triple(&self) -> &str {
+        &*self.name
+    }
     /// Architecture of the targeted machine
     ///
     /// Corresponds to the `#[cfg(target_arch = {})]` in Rust code.
@@ -232,6 +610,14 @@ impl TargetInfo {
     pub fn target_pointer_width(&self) -> &str {
         &*self.pointer_width
     }
+    /// CPU the code for the targeted machine is generated for
+    ///
+    /// This is the `cpu` of custom JSON targets, or the `-C target-cpu` in the
+    /// [`effective`](#method.effective) view of the target. `None` means the default CPU of the
+    /// target is used.
+    pub fn target_cpu(&self) -> Option<&str> {
+        self.cpu.as_ref().map(|c| &**c)
+    }
 
     /// Vendor of the targeted machine
     ///
@@ -248,7 +634,7 @@ impl TargetInfo {
     /// Corresponds to the `#[cfg({key} = {})]` in Rust code.
     ///
     /// This function behaves specially in regard to custom JSON targets and will always return
-    /// `false` for them currently.
+    /// `false` for them currently, unless they inherit from a builtin target.
     ///
     /// # Examples
     ///
@@ -264,7 +650,7 @@ impl TargetInfo {
     /// Return the value of an arbitrary configuration key
     ///
     /// This function behaves specially in regard to custom JSON targets and will rarely return
-    /// any extra target information.
+    /// any extra target information, unless they inherit from a builtin target.
     ///
     /// # Examples
     ///
@@ -284,6 +670,26 @@ impl TargetInfo {
             key => self.other_keys.iter().find(|t| t.0 == key).map(|t| &*t.1)
         }
     }
+
+    /// Return all the values of an arbitrary configuration key
+    ///
+    /// Some keys, such as `target_feature` or `target_has_atomic`, may have multiple values set
+    /// at once. `target_cfg_value` returns only the first one of those.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let info = TargetInfo::new().expect("target info");
+    /// let has_atomic_64 = info.target_cfg_values("target_has_atomic").contains(&"64");
+    /// ```
+    pub fn target_cfg_values<'a>(&'a self, key: &str) -> Vec<&'a str> {
+        match key {
+            "target_arch" | "target_os" | "target_env" | "target_endian" |
+            "target_pointer_width" => self.target_cfg_value(key).into_iter().collect(),
+            key => self.other_keys.iter().filter(|t| t.0 == key).map(|t| &*t.1).collect()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -557,6 +963,118 @@ mod tests {
         super::TargetInfo::from_str("my-great-target").err().unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn external_inherits_base() {
+        if super::TargetInfo::from_str("thumbv7em-none-eabihf").is_err() {
+            return;
+        }
+        let target = super::TargetInfo::from_str("src/my-great-thumb.json").unwrap();
+        assert_eq!(target.target_arch(), "arm");
+        assert_eq!(target.target_endian(), "little");
+        assert_eq!(target.target_pointer_width(), "32");
+        assert_eq!(target.target_os(), "greatos");
+        assert_eq!(target.target_vendor(), Some("great"));
+        let json = target.target_json().unwrap();
+        assert!(json.contains("\"llvm-target\": \"thumbv7em-none-eabihf\""));
+        assert!(json.contains("\"panic-strategy\": \"abort\""));
+        assert!(!json.contains(super::BASE_TARGET_KEY));
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn external_inherits_base_loadable() {
+        use std::env;
+        use std::ffi::OsString;
+        use std::fs::File;
+        use std::io::Write;
+        use std::process::Command;
+        if super::rustc_target_spec("thumbv7em-none-eabihf").is_none() {
+            return;
+        }
+        let target = super::TargetInfo::from_str("src/my-great-thumb.json").unwrap();
+        let json = target.target_json().unwrap();
+        assert!(json.contains("\"data-layout\""));
+        assert!(json.contains("\"target-pointer-width\": 32"));
+        let path = env::temp_dir().join(format!("my-great-thumb-{}.json", ::std::process::id()));
+        File::create(&path).unwrap().write_all(json.as_bytes()).unwrap();
+        let rustc = env::var_os("RUSTC").unwrap_or(OsString::from("rustc"));
+        let output = Command::new(rustc)
+                         .args(&["-Zunstable-options", "--print=cfg", "--target"]).arg(&path)
+                         .env("RUSTC_BOOTSTRAP", "1")
+                         .output().unwrap();
+        let _ = ::std::fs::remove_file(&path);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let cfg = String::from_utf8_lossy(&output.stdout);
+        assert!(cfg.contains("target_os=\"greatos\""));
+        assert!(cfg.contains("target_vendor=\"great\""));
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn external_from_json() {
+        let target = super::TargetInfo::from_json_str(include_str!("my-great-target.json"))
+            .unwrap();
+        external_is_correct(&target);
+        let spec: &[u8] = include_bytes!("my-great-target.json");
+        let target = super::TargetInfo::from_json_reader(spec).unwrap();
+        external_is_correct(&target);
+        super::TargetInfo::from_json_str("{\"arch\": \"x86_64\"}").err().unwrap();
+        super::TargetInfo::from_json_str("[]").err().unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde_json"))]
+    fn external_from_json() {
+        super::TargetInfo::from_json_str(include_str!("my-great-target.json")).err().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn external_search_order() {
+        use std::env;
+        use std::ffi::OsStr;
+        use std::fs;
+        use std::path::{Path, PathBuf};
+        use super::find_target_json_from;
+        let root = env::temp_dir().join(format!("target_build_utils-search-{}",
+                                                ::std::process::id()));
+        let target_dir = root.join("targets");
+        let sysroot = root.join("sysroot");
+        fs::create_dir_all(&target_dir).unwrap();
+        fs::create_dir_all(sysroot.join("lib/rustlib/great")).unwrap();
+        fs::create_dir_all(sysroot.join("lib/rustlib/my-great-target")).unwrap();
+        for path in &[target_dir.join("my-great-target.json"),
+                      sysroot.join("lib/rustlib/great/target.json"),
+                      sysroot.join("lib/rustlib/my-great-target/target.json")] {
+            fs::copy("src/my-great-target.json", path).unwrap();
+        }
+        let target_path = target_dir.as_os_str();
+        let no_sysroot = || -> Option<PathBuf> { panic!("sysroot queried") };
+        let find = |s, target_path: &OsStr| {
+            find_target_json_from(s, target_path, || Some(sysroot.clone()))
+        };
+
+        // 1. Relative to the current directory, the package directory, before anything else
+        let local = find_target_json_from("src/my-great-target.json", target_path, no_sysroot);
+        assert_eq!(local, Some(Path::new("src/my-great-target.json").canonicalize().unwrap()));
+        // 2. `RUST_TARGET_PATH`, before the sysroot
+        let in_target_path = target_dir.join("my-great-target.json").canonicalize().unwrap();
+        assert_eq!(find("my-great-target", target_path), Some(in_target_path));
+        // 3. The sysroot
+        assert_eq!(find("my-great-target", OsStr::new("")),
+                   Some(sysroot.join("lib/rustlib/my-great-target/target.json")));
+        assert_eq!(find("great", target_path), Some(sysroot.join("lib/rustlib/great/target.json")));
+        assert_eq!(find("nonexistent", target_path), None);
+        // Paths are not resolved against the ancestors of the package directory
+        let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").unwrap();
+        let dir_name = Path::new(&manifest_dir).file_name().unwrap();
+        let ancestor = Path::new(dir_name).join("src").join("my-great-target.json");
+        assert_eq!(find(ancestor.to_str().unwrap(), target_path), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+
     #[cfg(feature = "serde_json")]
     fn external_is_correct(ti: &super::TargetInfo) {
         assert_eq!(ti.target_arch(), "x86_64");
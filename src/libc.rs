@@ -0,0 +1,207 @@
+//! C library and dynamic linker of the target
+
+use {FloatAbi, TargetInfo};
+
+/// Flavour of the C library of the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Libc {
+    /// The GNU C library
+    Glibc,
+    /// musl, also used by OpenHarmony and Emscripten
+    Musl,
+    /// uClibc or uClibc-ng
+    Uclibc,
+    /// Bionic, the C library of Android
+    Bionic,
+    /// The legacy Microsoft C runtime, `msvcrt.dll`, used by MinGW
+    Msvcrt,
+    /// The Universal C runtime of Windows
+    Ucrt,
+    /// Newlib, commonly used on embedded systems
+    Newlib,
+    /// The C library of the WebAssembly System Interface
+    WasiLibc,
+    /// The C library which is part of the operating system, e.g. on macOS or FreeBSD
+    System,
+}
+
+impl TargetInfo {
+    /// Flavour of the C library of the target
+    ///
+    /// Returns `None` for targets without a C library, such as bare metal targets,
+    /// `x86_64-unknown-linux-none` or `wasm32-unknown-unknown`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::{TargetInfo, Libc};
+    /// let target = TargetInfo::from_str("aarch64-linux-android").expect("target info");
+    /// assert_eq!(target.libc(), Some(Libc::Bionic));
+    /// ```
+    pub fn libc(&self) -> Option<Libc> {
+        match (self.target_os(), self.target_env()) {
+            ("android", _) => Some(Libc::Bionic),
+            ("windows", "msvc") => Some(Libc::Ucrt),
+            // The `*-pc-windows-gnullvm` targets
+            ("windows", "gnu") if self.target_cfg_value("target_abi") == Some("llvm") => {
+                Some(Libc::Ucrt)
+            }
+            ("windows", "gnu") => Some(Libc::Msvcrt),
+            ("wasi", _) => Some(Libc::WasiLibc),
+            ("emscripten", _) | (_, "musl") | (_, "ohos") => Some(Libc::Musl),
+            ("vxworks", _) => Some(Libc::System),
+            (_, "gnu") => Some(Libc::Glibc),
+            (_, "uclibc") => Some(Libc::Uclibc),
+            (_, "newlib") => Some(Libc::Newlib),
+            ("linux", "") | ("none", _) | ("uefi", _) | ("unknown", _) | ("", _) => None,
+            _ => Some(Libc::System),
+        }
+    }
+
+    /// Path of the default dynamic linker, also known as the program interpreter, of the target
+    ///
+    /// This is the `PT_INTERP` of dynamically linked ELF executables, e.g.
+    /// `/lib64/ld-linux-x86-64.so.2` for `x86_64-unknown-linux-gnu` or `/system/bin/linker64`
+    /// for `aarch64-linux-android`, and `/usr/lib/dyld` for Apple platforms. Returns `None` if
+    /// the target has no dynamic linker or it is not known.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("armv7-unknown-linux-musleabihf").expect("target info");
+    /// assert_eq!(target.dynamic_linker().unwrap(), "/lib/ld-musl-armhf.so.1");
+    /// ```
+    pub fn dynamic_linker(&self) -> Option<String> {
+        let arch = self.target_arch();
+        let sixty_four = self.target_pointer_width() == "64";
+        let big = self.target_endian() == "big";
+        let hard_float = self.arm_info().map(|arm| arm.float_abi == FloatAbi::Hard)
+                             .unwrap_or(false);
+        let linker = match self.libc() {
+            Some(Libc::Glibc) if self.target_os() == "linux" => match arch {
+                "x86_64" if sixty_four => "/lib64/ld-linux-x86-64.so.2",
+                "x86_64" => "/libx32/ld-linux-x32.so.2",
+                "x86" => "/lib/ld-linux.so.2",
+                "aarch64" if big => "/lib/ld-linux-aarch64_be.so.1",
+                "aarch64" => "/lib/ld-linux-aarch64.so.1",
+                "arm" if hard_float => "/lib/ld-linux-armhf.so.3",
+                "arm" => "/lib/ld-linux.so.3",
+                "powerpc" | "mips" | "m68k" => "/lib/ld.so.1",
+                "powerpc64" if big => "/lib64/ld64.so.1",
+                "powerpc64" => "/lib64/ld64.so.2",
+                "mips64" => "/lib64/ld.so.1",
+                // MIPS R6 requires the IEEE 754-2008 NaN encoding
+                "mips32r6" => "/lib/ld-linux-mipsn8.so.1",
+                "mips64r6" => "/lib64/ld-linux-mipsn8.so.1",
+                "s390x" => "/lib/ld64.so.1",
+                "sparc" => "/lib/ld-linux.so.2",
+                "sparc64" => "/lib64/ld-linux.so.2",
+                "riscv32" => "/lib/ld-linux-riscv32-ilp32d.so.1",
+                "riscv64" => "/lib/ld-linux-riscv64-lp64d.so.1",
+                "loongarch64" => "/lib64/ld-linux-loongarch-lp64d.so.1",
+                _ => return None,
+            },
+            Some(Libc::Musl) if self.target_os() == "linux" => {
+                let name = match arch {
+                    "x86" => String::from("i386"),
+                    "arm" => format!("arm{}{}", if big { "eb" } else { "" },
+                                     if hard_float { "hf" } else { "" }),
+                    "aarch64" if big => String::from("aarch64_be"),
+                    // The builtin targets for 32-bit MIPS with musl use the soft-float ABI
+                    "mips" => {
+                        let soft_float = self.spec_value("features")
+                                             .map(|f| f.contains("+soft-float"))
+                                             .unwrap_or(true);
+                        format!("mips{}{}", if big { "" } else { "el" },
+                                if soft_float { "-sf" } else { "" })
+                    }
+                    "mips64" if !big => String::from("mips64el"),
+                    "powerpc64" if !big => String::from("powerpc64le"),
+                    arch => String::from(arch),
+                };
+                return Some(format!("/lib/ld-musl-{}.so.1", name));
+            }
+            Some(Libc::Bionic) if sixty_four => "/system/bin/linker64",
+            Some(Libc::Bionic) => "/system/bin/linker",
+            _ if self.is_like_osx() => "/usr/lib/dyld",
+            _ => match self.target_os() {
+                "freebsd" => "/libexec/ld-elf.so.1",
+                "dragonfly" => "/libexec/ld-elf.so.2",
+                "netbsd" => "/usr/libexec/ld.elf_so",
+                "openbsd" => "/usr/libexec/ld.so",
+                "solaris" | "illumos" if sixty_four => "/lib/64/ld.so.1",
+                "solaris" | "illumos" => "/lib/ld.so.1",
+                _ => return None,
+            },
+        };
+        Some(String::from(linker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Libc, TargetInfo};
+
+    #[test]
+    fn libc() {
+        macro_rules! check_libc {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.libc(), $expected, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_libc!("x86_64-unknown-linux-gnu" => Some(Libc::Glibc),
+                    "x86_64-unknown-linux-musl" => Some(Libc::Musl),
+                    "aarch64-linux-android" => Some(Libc::Bionic),
+                    "x86_64-pc-windows-gnu" => Some(Libc::Msvcrt),
+                    "x86_64-pc-windows-msvc" => Some(Libc::Ucrt),
+                    "x86_64-pc-windows-gnullvm" => Some(Libc::Ucrt),
+                    "x86_64-wrs-vxworks" => Some(Libc::System),
+                    "x86_64-unknown-linux-none" => None,
+                    "armv7-sony-vita-newlibeabihf" => Some(Libc::Newlib),
+                    "wasm32-wasip1" => Some(Libc::WasiLibc),
+                    "x86_64-unknown-freebsd" => Some(Libc::System),
+                    "wasm32-unknown-unknown" => None,
+                    "thumbv7em-none-eabihf" => None);
+    }
+
+    #[test]
+    fn dynamic_linker() {
+        macro_rules! check_linker {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.dynamic_linker().as_ref().map(|s| &**s), $expected,
+                                   "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_linker!("x86_64-unknown-linux-gnu" => Some("/lib64/ld-linux-x86-64.so.2"),
+                      "x86_64-unknown-linux-gnux32" => Some("/libx32/ld-linux-x32.so.2"),
+                      "i686-unknown-linux-gnu" => Some("/lib/ld-linux.so.2"),
+                      "aarch64-unknown-linux-gnu" => Some("/lib/ld-linux-aarch64.so.1"),
+                      "armv7-unknown-linux-gnueabihf" => Some("/lib/ld-linux-armhf.so.3"),
+                      "arm-unknown-linux-gnueabi" => Some("/lib/ld-linux.so.3"),
+                      "powerpc64le-unknown-linux-gnu" => Some("/lib64/ld64.so.2"),
+                      "mipsisa32r6-unknown-linux-gnu" => Some("/lib/ld-linux-mipsn8.so.1"),
+                      "mipsisa64r6el-unknown-linux-gnuabi64" => Some("/lib64/ld-linux-mipsn8.so.1"),
+                      "riscv64gc-unknown-linux-gnu" => Some("/lib/ld-linux-riscv64-lp64d.so.1"),
+                      "x86_64-unknown-linux-musl" => Some("/lib/ld-musl-x86_64.so.1"),
+                      "i686-unknown-linux-musl" => Some("/lib/ld-musl-i386.so.1"),
+                      "aarch64-unknown-linux-musl" => Some("/lib/ld-musl-aarch64.so.1"),
+                      "armv7-unknown-linux-musleabihf" => Some("/lib/ld-musl-armhf.so.1"),
+                      "mipsel-unknown-linux-musl" => Some("/lib/ld-musl-mipsel-sf.so.1"),
+                      "mips64el-unknown-linux-muslabi64" => Some("/lib/ld-musl-mips64el.so.1"),
+                      "aarch64-linux-android" => Some("/system/bin/linker64"),
+                      "armv7-linux-androideabi" => Some("/system/bin/linker"),
+                      "aarch64-apple-darwin" => Some("/usr/lib/dyld"),
+                      "x86_64-unknown-freebsd" => Some("/libexec/ld-elf.so.1"),
+                      "x86_64-pc-windows-msvc" => None,
+                      "thumbv7em-none-eabihf" => None);
+    }
+}
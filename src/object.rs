@@ -0,0 +1,391 @@
+//! Object file formats and machine identifiers of the target
+
+use {FloatAbi, TargetInfo};
+
+/// Format of the object files, libraries and executables of the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectFormat {
+    /// Executable and Linkable Format, used by most Unix-like and bare metal targets
+    Elf,
+    /// Mach-O, used by Apple platforms
+    MachO,
+    /// COFF, and PE for linked images, used by Windows and UEFI
+    Coff,
+    /// WebAssembly modules
+    Wasm,
+    /// XCOFF, used by AIX
+    Xcoff,
+}
+
+/// Format of the debug information of the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebuginfoFormat {
+    /// DWARF, embedded in the object files or split into separate files such as `.dSYM`
+    /// bundles or `.dwo` files
+    Dwarf,
+    /// CodeView, stored in PDB files by the MSVC linker
+    CodeView,
+}
+
+/// ELF identification of the target
+///
+/// See `TargetInfo::elf_machine`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElfMachine {
+    /// The `e_machine` field, e.g. `62` (`EM_X86_64`)
+    pub machine: u16,
+    /// The `EI_CLASS` byte of `e_ident`: `1` (`ELFCLASS32`) or `2` (`ELFCLASS64`)
+    pub class: u8,
+    /// The `EI_DATA` byte of `e_ident`: `1` (`ELFDATA2LSB`) or `2` (`ELFDATA2MSB`)
+    pub data: u8,
+    /// Bits of the `e_flags` field which are expected to be set
+    ///
+    /// These describe the ABI on ARM (the EABI version and the hard-float ABI) and RISC-V
+    /// (compressed instructions, the RV32E base and the floating point ABI). Other bits may be
+    /// set as well.
+    pub flags: u32,
+}
+
+/// Mach-O CPU type of the target
+///
+/// See `TargetInfo::macho_cpu`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachOCpu {
+    /// The `cputype` field, e.g. `0x0100000c` (`CPU_TYPE_ARM64`)
+    pub cputype: u32,
+    /// The `cpusubtype` field, without the capability bits in the high byte
+    pub cpusubtype: u32,
+}
+
+impl TargetInfo {
+    /// Architecture component of the LLVM triple, e.g. `armv7s` for `armv7s-apple-ios`
+    fn object_arch(&self) -> String {
+        self.llvm_triple().split('-').next().unwrap_or("").to_owned()
+    }
+
+    /// `e_flags` of RISC-V ELF files
+    ///
+    /// The float ABI is that of the `llvm-abiname` of custom JSON targets, and compressed
+    /// instructions are enabled by the `c` or `zca` target features. The extensions named by the
+    /// architecture component of the triple, e.g. `gc` in `riscv64gc`, are used otherwise.
+    fn riscv_elf_flags(&self) -> u32 {
+        let arch = self.object_arch();
+        let extensions = arch.get(7..).unwrap_or("");
+        let spec_features = self.spec_value("features").unwrap_or_default();
+        let spec_feature = |sign: char, name: &str| {
+            spec_features.split(',').any(|f| f.starts_with(sign) && &f[1..] == name)
+        };
+        let has_feature = |name: &str| {
+            !spec_feature('-', name) &&
+            (spec_feature('+', name) || extensions.contains(name) ||
+             self.target_cfg_values("target_feature").contains(&name))
+        };
+        let mut flags = 0;
+        if has_feature("c") || has_feature("zca") {
+            flags |= 0x1; // EF_RISCV_RVC
+        }
+        match self.spec_value("llvm-abiname") {
+            Some(abi) => {
+                if abi.ends_with('e') {
+                    flags |= 0x8; // EF_RISCV_RVE
+                }
+                flags |= match abi.trim_end_matches('e').chars().last() {
+                    Some('f') => 0x2, // EF_RISCV_FLOAT_ABI_SINGLE
+                    Some('d') => 0x4, // EF_RISCV_FLOAT_ABI_DOUBLE
+                    Some('q') => 0x6, // EF_RISCV_FLOAT_ABI_QUAD
+                    _ => 0,
+                };
+            }
+            None => {
+                if extensions.starts_with('e') {
+                    flags |= 0x8; // EF_RISCV_RVE
+                }
+                if extensions.starts_with('g') || extensions.contains('d') {
+                    flags |= 0x4; // EF_RISCV_FLOAT_ABI_DOUBLE
+                } else if extensions.contains('f') {
+                    flags |= 0x2; // EF_RISCV_FLOAT_ABI_SINGLE
+                }
+            }
+        }
+        flags
+    }
+
+    /// ELF identification of the target
+    ///
+    /// Returns `None` if the target does not use ELF or its architecture is not known.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("x86_64-unknown-linux-gnu").expect("target info");
+    /// let elf = target.elf_machine().unwrap();
+    /// assert_eq!((elf.machine, elf.class, elf.data), (62, 2, 1));
+    /// ```
+    pub fn elf_machine(&self) -> Option<ElfMachine> {
+        if self.object_format() != ObjectFormat::Elf {
+            return None;
+        }
+        let sixty_four = self.target_pointer_width() == "64";
+        let machine = match self.target_arch() {
+            "x86" => 3,
+            "x86_64" => 62,
+            "arm" => 40,
+            "aarch64" => 183,
+            "mips" | "mips64" | "mips32r6" | "mips64r6" => 8,
+            "powerpc" => 20,
+            "powerpc64" => 21,
+            "s390x" => 22,
+            // Linux uses the V8+ ABI
+            "sparc" => if self.target_os() == "linux" { 18 } else { 2 },
+            "sparc64" => 43,
+            "riscv32" | "riscv64" => 243,
+            "loongarch32" | "loongarch64" => 258,
+            "m68k" => 4,
+            "hexagon" => 164,
+            "avr" => 83,
+            "msp430" => 105,
+            "bpf" => 247,
+            "csky" => 252,
+            "xtensa" => 94,
+            _ => return None,
+        };
+        let flags = match self.target_arch() {
+            // EF_ARM_EABI_VER5, with EF_ARM_ABI_FLOAT_HARD for the hard-float ABI
+            "arm" => match self.arm_info() {
+                Some(ref arm) if arm.float_abi == FloatAbi::Hard => 0x0500_0400,
+                _ => 0x0500_0000,
+            },
+            "riscv32" | "riscv64" => self.riscv_elf_flags(),
+            _ => 0,
+        };
+        Some(ElfMachine {
+            machine: machine,
+            class: if sixty_four { 2 } else { 1 },
+            data: if self.target_endian() == "big" { 2 } else { 1 },
+            flags: flags,
+        })
+    }
+
+    /// `Machine` field of the PE/COFF header for the target
+    ///
+    /// Returns `None` if the target does not use COFF or its architecture is not known.
+    pub fn pe_machine(&self) -> Option<u16> {
+        if self.object_format() != ObjectFormat::Coff {
+            return None;
+        }
+        match self.target_arch() {
+            "x86" => Some(0x14c),
+            "x86_64" => Some(0x8664),
+            "arm" => Some(0x1c4),
+            "aarch64" => Some(0xaa64),
+            "arm64ec" => Some(0xa641),
+            _ => None,
+        }
+    }
+
+    /// Mach-O CPU type of the target
+    ///
+    /// Returns `None` if the target does not use Mach-O or its architecture is not known.
+    pub fn macho_cpu(&self) -> Option<MachOCpu> {
+        if self.object_format() != ObjectFormat::MachO {
+            return None;
+        }
+        let (cputype, cpusubtype) = match (self.target_arch(), &*self.object_arch()) {
+            ("x86", _) => (7, 3),
+            ("x86_64", "x86_64h") => (0x0100_0007, 8),
+            ("x86_64", _) => (0x0100_0007, 3),
+            ("aarch64", "arm64e") => (0x0100_000c, 2),
+            ("aarch64", _) if self.target_pointer_width() == "32" => (0x0200_000c, 1),
+            ("aarch64", _) => (0x0100_000c, 0),
+            ("arm", "armv7s") => (12, 11),
+            ("arm", "armv7k") => (12, 12),
+            ("arm", _) => (12, 9),
+            ("powerpc", _) => (18, 0),
+            _ => return None,
+        };
+        Some(MachOCpu { cputype: cputype, cpusubtype: cpusubtype })
+    }
+
+    /// Format of the object files of the target
+    ///
+    /// This is the `binary-format` field of custom JSON targets, if present. Otherwise it is
+    /// derived from the OS and architecture of the target, taking the `is-like-darwin`,
+    /// `is-like-windows`, `is-like-wasm` and `is-like-aix` fields of custom JSON targets into
+    /// account.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::{TargetInfo, ObjectFormat};
+    /// let target = TargetInfo::new().expect("target info");
+    /// let text_section = match target.object_format() {
+    ///     ObjectFormat::MachO => "__TEXT,__text",
+    ///     _ => ".text",
+    /// };
+    /// ```
+    pub fn object_format(&self) -> ObjectFormat {
+        let arch = self.target_arch();
+        match self.spec_value("binary-format").as_ref().map(|f| &**f) {
+            Some("elf") => return ObjectFormat::Elf,
+            Some("mach-o") => return ObjectFormat::MachO,
+            Some("coff") => return ObjectFormat::Coff,
+            Some("wasm") => return ObjectFormat::Wasm,
+            Some("xcoff") => return ObjectFormat::Xcoff,
+            _ => {}
+        }
+        if self.is_like_osx() {
+            ObjectFormat::MachO
+        } else if self.is_like_windows() {
+            ObjectFormat::Coff
+        } else if self.spec_flag("is-like-wasm", arch == "wasm32" || arch == "wasm64") {
+            ObjectFormat::Wasm
+        } else if self.spec_flag("is-like-aix", self.target_os() == "aix") {
+            ObjectFormat::Xcoff
+        } else {
+            ObjectFormat::Elf
+        }
+    }
+
+    /// Format of the debug information of the target
+    ///
+    /// This is CodeView for targets using the MSVC toolchain, as per the `is-like-msvc` field of
+    /// custom JSON targets, and DWARF otherwise.
+    pub fn debuginfo_format(&self) -> DebuginfoFormat {
+        if self.is_like_msvc() {
+            DebuginfoFormat::CodeView
+        } else {
+            DebuginfoFormat::Dwarf
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {DebuginfoFormat, ElfMachine, MachOCpu, ObjectFormat, TargetInfo};
+
+    #[test]
+    fn formats() {
+        macro_rules! check_formats {
+            ($($str: expr => ($object: expr, $debuginfo: expr)),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.object_format(), $object, "{}", $str);
+                        assert_eq!(ti.debuginfo_format(), $debuginfo, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_formats!("x86_64-unknown-linux-gnu" => (ObjectFormat::Elf, DebuginfoFormat::Dwarf),
+                       "thumbv7em-none-eabihf" => (ObjectFormat::Elf, DebuginfoFormat::Dwarf),
+                       "aarch64-apple-darwin" => (ObjectFormat::MachO, DebuginfoFormat::Dwarf),
+                       "x86_64-pc-windows-msvc" =>
+                           (ObjectFormat::Coff, DebuginfoFormat::CodeView),
+                       "x86_64-pc-windows-gnu" => (ObjectFormat::Coff, DebuginfoFormat::Dwarf),
+                       "x86_64-unknown-uefi" => (ObjectFormat::Coff, DebuginfoFormat::CodeView),
+                       "x86_64-pc-cygwin" => (ObjectFormat::Coff, DebuginfoFormat::Dwarf),
+                       "wasm32-unknown-unknown" => (ObjectFormat::Wasm, DebuginfoFormat::Dwarf),
+                       "powerpc64-ibm-aix" => (ObjectFormat::Xcoff, DebuginfoFormat::Dwarf));
+    }
+
+    #[test]
+    fn machines() {
+        let elf = |machine, class, data, flags| {
+            Some(ElfMachine { machine: machine, class: class, data: data, flags: flags })
+        };
+        macro_rules! check_elf {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.elf_machine(), $expected, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_elf!("x86_64-unknown-linux-gnu" => elf(62, 2, 1, 0),
+                   "x86_64-unknown-linux-gnux32" => elf(62, 1, 1, 0),
+                   "i686-unknown-linux-gnu" => elf(3, 1, 1, 0),
+                   "aarch64-unknown-linux-gnu" => elf(183, 2, 1, 0),
+                   "armv7-unknown-linux-gnueabihf" => elf(40, 1, 1, 0x0500_0400),
+                   "thumbv6m-none-eabi" => elf(40, 1, 1, 0x0500_0000),
+                   "powerpc64-unknown-linux-gnu" => elf(21, 2, 2, 0),
+                   "riscv64gc-unknown-linux-gnu" => elf(243, 2, 1, 0x5),
+                   "riscv32imafc-unknown-none-elf" => elf(243, 1, 1, 0x3),
+                   "riscv32emc-unknown-none-elf" => elf(243, 1, 1, 0x9),
+                   "x86_64-pc-windows-gnu" => None);
+
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-msvc") {
+            assert_eq!(ti.pe_machine(), Some(0x8664));
+            assert_eq!(ti.macho_cpu(), None);
+        }
+        if let Ok(ti) = TargetInfo::from_str("aarch64-pc-windows-msvc") {
+            assert_eq!(ti.pe_machine(), Some(0xaa64));
+        }
+
+        let macho = |cputype, cpusubtype| {
+            Some(MachOCpu { cputype: cputype, cpusubtype: cpusubtype })
+        };
+        macro_rules! check_macho {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.macho_cpu(), $expected, "{}", $str);
+                    }
+                )+
+            }
+        }
+        check_macho!("x86_64-apple-darwin" => macho(0x0100_0007, 3),
+                     "aarch64-apple-darwin" => macho(0x0100_000c, 0),
+                     "arm64e-apple-darwin" => macho(0x0100_000c, 2),
+                     "arm64_32-apple-watchos" => macho(0x0200_000c, 1),
+                     "armv7s-apple-ios" => macho(12, 11),
+                     "x86_64-unknown-linux-gnu" => None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn formats_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "arch": "x86_64",
+            "os": "myos",
+            "target-endian": "little",
+            "target-pointer-width": "64",
+            "is-like-windows": true,
+            "is-like-msvc": true
+        }"#).unwrap();
+        assert_eq!(ti.object_format(), ObjectFormat::Coff);
+        assert_eq!(ti.debuginfo_format(), DebuginfoFormat::CodeView);
+
+        let ti = TargetInfo::from_json_str(r#"{
+            "base-target": "x86_64-unknown-linux-gnu",
+            "binary-format": "coff"
+        }"#).unwrap();
+        assert_eq!(ti.object_format(), ObjectFormat::Coff);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn machines_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "arch": "riscv64",
+            "os": "none",
+            "llvm-target": "riscv64",
+            "target-endian": "little",
+            "target-pointer-width": "64",
+            "llvm-abiname": "lp64f",
+            "features": "+m,+a,+f,+c"
+        }"#).unwrap();
+        assert_eq!(ti.elf_machine().unwrap().flags, 0x3);
+
+        let ti = TargetInfo::from_json_str(r#"{
+            "arch": "riscv32",
+            "os": "none",
+            "llvm-target": "riscv32imac-unknown-none-elf",
+            "target-endian": "little",
+            "target-pointer-width": "32",
+            "llvm-abiname": "ilp32e",
+            "features": "+e,+m,-c"
+        }"#).unwrap();
+        assert_eq!(ti.elf_machine().unwrap().flags, 0x8);
+    }
+}
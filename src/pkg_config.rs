@@ -0,0 +1,245 @@
+//! Target-aware pkg-config invocation
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use {Error, TargetInfo};
+
+/// pkg-config setup for finding the libraries of the target rather than those of the host
+///
+/// When the target differs from the host, the host pkg-config would silently report the
+/// libraries of the host system. To avoid that, the pkg-config command is set up as follows:
+///
+/// * if a sysroot was specified, the `pkg-config` binary is used with `PKG_CONFIG_SYSROOT_DIR`
+///   set to the sysroot and `PKG_CONFIG_LIBDIR` restricted to the directories in the sysroot.
+///   `PKG_CONFIG_PATH` is removed from the environment, unless the host pkg-config was allowed,
+///   as it usually points to the `.pc` files of the host;
+/// * otherwise, the target-prefixed binary (e.g. `aarch64-linux-gnu-pkg-config`) is used, if
+///   it can be found in `PATH`;
+/// * otherwise, the host `pkg-config` is used only if that was explicitly allowed with
+///   `PkgConfig::allow_host_pkg_config`, and `Error::HostPkgConfig` is returned if not.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::env;
+/// use target_build_utils::{TargetInfo, PkgConfig};
+/// let target = TargetInfo::new().expect("could not get target");
+/// let host = env::var("HOST").expect("HOST");
+/// let output = PkgConfig::new(&target, &host)
+///     .sysroot("/usr/aarch64-linux-gnu")
+///     .command()
+///     .expect("pkg-config for the target")
+///     .args(&["--libs", "zlib"])
+///     .output();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PkgConfig<'a> {
+    target: &'a TargetInfo,
+    host: String,
+    sysroot: Option<PathBuf>,
+    allow_host: bool,
+}
+
+impl<'a> PkgConfig<'a> {
+    /// Create a pkg-config setup for the target, when building on the `host`
+    ///
+    /// In build scripts, the host triple is available in the `HOST` environment variable.
+    pub fn new(target: &'a TargetInfo, host: &str) -> PkgConfig<'a> {
+        PkgConfig {
+            target: target,
+            host: host.into(),
+            sysroot: None,
+            allow_host: false,
+        }
+    }
+
+    /// Set the sysroot of the target system
+    pub fn sysroot<P: AsRef<Path>>(&mut self, sysroot: P) -> &mut PkgConfig<'a> {
+        self.sysroot = Some(sysroot.as_ref().to_path_buf());
+        self
+    }
+
+    /// Allow using the host pkg-config when cross-compiling without a sysroot
+    ///
+    /// This also keeps `PKG_CONFIG_PATH` when a sysroot was specified.
+    pub fn allow_host_pkg_config(&mut self, allow: bool) -> &mut PkgConfig<'a> {
+        self.allow_host = allow;
+        self
+    }
+
+    /// Whether the target differs from the host
+    pub fn is_cross(&self) -> bool {
+        self.host != self.target.target_triple()
+    }
+
+    /// Name of the target-prefixed pkg-config binary, e.g. `aarch64-linux-gnu-pkg-config`
+    ///
+    /// For Android this is the triple without the API level, as used by the binutils of the NDK,
+    /// e.g. `aarch64-linux-android-pkg-config` or `arm-linux-androideabi-pkg-config`.
+    pub fn binary_name(&self) -> String {
+        let triple = self.target.c_compiler_triple();
+        if self.target.target_os() != "android" {
+            return format!("{}-pkg-config", triple);
+        }
+        let triple = triple.trim_end_matches(|c: char| c.is_ascii_digit());
+        let triple = if self.target.target_arch() == "arm" {
+            "arm-linux-androideabi"
+        } else {
+            triple
+        };
+        format!("{}-pkg-config", triple)
+    }
+
+    /// Value of `PKG_CONFIG_SYSROOT_DIR` for the target, if a sysroot was specified
+    pub fn sysroot_dir(&self) -> Option<&Path> {
+        self.sysroot.as_ref().map(|p| &**p)
+    }
+
+    /// Value of `PKG_CONFIG_LIBDIR` for the target, if a sysroot was specified
+    ///
+    /// This contains `usr/lib/pkgconfig` and `usr/share/pkgconfig` in the sysroot, as well as the
+    /// multiarch `usr/lib/{triple}/pkgconfig` for Linux targets.
+    pub fn libdir(&self) -> Option<OsString> {
+        let sysroot = match self.sysroot {
+            Some(ref sysroot) => sysroot,
+            None => return None,
+        };
+        let mut dirs = vec![sysroot.join("usr/lib/pkgconfig"),
+                            sysroot.join("usr/share/pkgconfig")];
+        if self.target.target_os() == "linux" {
+            let multiarch = match self.target.target_arch() {
+                "x86" => format!("i386-linux-{}", self.target.target_env()),
+                _ => self.target.c_compiler_triple(),
+            };
+            dirs.insert(0, sysroot.join("usr/lib").join(multiarch + "/pkgconfig"));
+        }
+        env::join_paths(dirs).ok()
+    }
+
+    /// Environment variables to set for pkg-config
+    pub fn env(&self) -> Vec<(&'static str, OsString)> {
+        let mut env = Vec::new();
+        if let Some(sysroot) = self.sysroot_dir() {
+            env.push(("PKG_CONFIG_SYSROOT_DIR", sysroot.as_os_str().to_owned()));
+        }
+        if let Some(libdir) = self.libdir() {
+            env.push(("PKG_CONFIG_LIBDIR", libdir));
+        }
+        env
+    }
+
+    /// Environment variables to remove for pkg-config
+    ///
+    /// This is `PKG_CONFIG_PATH` if a sysroot was specified and the host pkg-config was not
+    /// allowed with `PkgConfig::allow_host_pkg_config`.
+    pub fn env_remove(&self) -> Vec<&'static str> {
+        if self.sysroot.is_some() && !self.allow_host {
+            vec!["PKG_CONFIG_PATH"]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The pkg-config command set up for the target
+    pub fn command(&self) -> Result<Command, Error> {
+        let binary = if !self.is_cross() || self.sysroot.is_some() {
+            PathBuf::from("pkg-config")
+        } else if let Some(binary) = find_in_path(&self.binary_name(),
+                                                  &env::var_os("PATH").unwrap_or_default()) {
+            binary
+        } else if self.allow_host {
+            PathBuf::from("pkg-config")
+        } else {
+            return Err(Error::HostPkgConfig);
+        };
+        let mut command = Command::new(binary);
+        for key in self.env_remove() {
+            command.env_remove(key);
+        }
+        for (key, value) in self.env() {
+            command.env(key, value);
+        }
+        Ok(command)
+    }
+}
+
+/// Find the executable in the directories listed in `path`, the value of the `PATH` variable
+fn find_in_path(name: &str, path: &OsStr) -> Option<PathBuf> {
+    for dir in env::split_paths(path) {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        // The name may contain dots, e.g. `x86_64-pc-solaris2.11-pkg-config`
+        if !env::consts::EXE_SUFFIX.is_empty() {
+            let candidate = dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::Path;
+    use {PkgConfig, TargetInfo};
+
+    #[test]
+    fn pkg_config() {
+        if let Ok(ti) = TargetInfo::from_str("aarch64-unknown-linux-gnu") {
+            let pkg_config = PkgConfig::new(&ti, "aarch64-unknown-linux-gnu");
+            assert!(!pkg_config.is_cross());
+            assert!(pkg_config.env().is_empty());
+            assert!(pkg_config.env_remove().is_empty());
+            pkg_config.command().unwrap();
+
+            let mut pkg_config = PkgConfig::new(&ti, "x86_64-unknown-linux-gnu");
+            assert!(pkg_config.is_cross());
+            assert_eq!(pkg_config.binary_name(), "aarch64-linux-gnu-pkg-config");
+            pkg_config.allow_host_pkg_config(true).command().unwrap();
+
+            pkg_config.sysroot("/sysroot");
+            assert!(pkg_config.env_remove().is_empty());
+            pkg_config.allow_host_pkg_config(false);
+            assert_eq!(pkg_config.env_remove(), ["PKG_CONFIG_PATH"]);
+            let env = pkg_config.env();
+            assert_eq!(env[0], ("PKG_CONFIG_SYSROOT_DIR", "/sysroot".into()));
+            let sysroot = Path::new("/sysroot");
+            assert_eq!(env::split_paths(&env[1].1).collect::<Vec<_>>(),
+                       [sysroot.join("usr/lib/aarch64-linux-gnu/pkgconfig"),
+                        sysroot.join("usr/lib/pkgconfig"),
+                        sysroot.join("usr/share/pkgconfig")]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("thumbv7em-none-eabihf") {
+            PkgConfig::new(&ti, "x86_64-unknown-linux-gnu").command().err().unwrap();
+        }
+        if let Ok(ti) = TargetInfo::from_str("aarch64-linux-android") {
+            assert_eq!(PkgConfig::new(&ti, "x86_64-unknown-linux-gnu").binary_name(),
+                       "aarch64-linux-android-pkg-config");
+        }
+        if let Ok(ti) = TargetInfo::from_str("armv7-linux-androideabi") {
+            assert_eq!(PkgConfig::new(&ti, "x86_64-unknown-linux-gnu").binary_name(),
+                       "arm-linux-androideabi-pkg-config");
+        }
+    }
+
+    #[test]
+    fn find_in_path() {
+        use std::fs::{self, File};
+        let dir = env::temp_dir().join(format!("pkg-config-path-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let name = "x86_64-pc-solaris2.11-pkg-config";
+        let binary = dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+        File::create(&binary).unwrap();
+        let path = env::join_paths(&[Path::new("/nonexistent"), &dir]).unwrap();
+        let found = super::find_in_path(name, &path);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(found, Some(binary));
+    }
+}
@@ -0,0 +1,218 @@
+//! Effective target configuration after applying the flags passed to rustc
+
+use std::borrow::Cow;
+use std::env;
+use std::ffi::OsString;
+
+use {Cfg, Error, TargetInfo};
+
+/// Flags from `CARGO_ENCODED_RUSTFLAGS`, or from `RUSTFLAGS` if the former is not set
+///
+/// The flags in `CARGO_ENCODED_RUSTFLAGS` are separated by the `0x1f` character, while the ones
+/// in `RUSTFLAGS` are separated by whitespace.
+fn split_rustflags(encoded: Option<OsString>, plain: Option<OsString>) -> Vec<String> {
+    if let Some(encoded) = encoded {
+        encoded.to_string_lossy().split('\x1f').filter(|f| !f.is_empty()).map(String::from)
+               .collect()
+    } else if let Some(plain) = plain {
+        plain.to_string_lossy().split_whitespace().map(String::from).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+impl TargetInfo {
+    /// The target as seen by rustc invoked by cargo, with the flags in `CARGO_ENCODED_RUSTFLAGS`
+    /// or `RUSTFLAGS` applied
+    ///
+    /// Cargo sets `CARGO_ENCODED_RUSTFLAGS` for build scripts. `RUSTFLAGS` is only consulted if
+    /// it is not set. See [`with_rustflags`](#method.with_rustflags) for the flags which are
+    /// taken into account.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().and_then(|t| t.effective()).expect("target info");
+    /// let crt_static = target.target_cfg_values("target_feature").contains(&"crt-static");
+    /// ```
+    pub fn effective(&self) -> Result<TargetInfo, Error> {
+        let flags = split_rustflags(env::var_os("CARGO_ENCODED_RUSTFLAGS"),
+                                    env::var_os("RUSTFLAGS"));
+        self.with_rustflags(&flags)
+    }
+
+    /// Whether the C runtime is linked statically
+    ///
+    /// The default of the target is the presence of the `crt-static` target feature for builtin
+    /// targets, and the `crt-static-default` field of custom JSON targets. It is overridden by
+    /// `-C target-feature=+crt-static` or `-C target-feature=-crt-static` in
+    /// `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS`, unless the custom JSON target sets
+    /// `crt-static-respected` to `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// if target.crt_static().expect("invalid RUSTFLAGS") {
+    ///     println!("cargo:rustc-link-lib=static=stdc++");
+    /// }
+    /// ```
+    pub fn crt_static(&self) -> Result<bool, Error> {
+        let flags = split_rustflags(env::var_os("CARGO_ENCODED_RUSTFLAGS"),
+                                    env::var_os("RUSTFLAGS"));
+        self.crt_static_with(&flags)
+    }
+
+    fn crt_static_with<S: AsRef<str>>(&self, flags: &[S]) -> Result<bool, Error> {
+        let has_feature = |t: &TargetInfo| {
+            t.target_cfg_values("target_feature").contains(&"crt-static")
+        };
+        let default = match self.spec_value("crt-static-default") {
+            Some(default) => default == "true",
+            None => has_feature(self),
+        };
+        if self.spec_value("crt-static-respected").map(|r| r == "false").unwrap_or(false) {
+            return Ok(default);
+        }
+        let default_flag = if default { "-Ctarget-feature=+crt-static" } else {
+            "-Ctarget-feature=-crt-static"
+        };
+        let target = try!(self.with_rustflags(&[default_flag]));
+        Ok(has_feature(&try!(target.with_rustflags(flags))))
+    }
+
+    /// The target as seen by rustc invoked with the given flags
+    ///
+    /// The following flags are taken into account, in order:
+    ///
+    /// * `--cfg {name}` and `--cfg {key}="{value}"` set additional configuration switches and
+    ///   keys;
+    /// * `-C target-feature=+{feature},-{feature}` add and remove `target_feature` values. Note
+    ///   that features implied by the added ones are not added;
+    /// * `-C target-cpu={cpu}` sets the [`target_cpu`](#method.target_cpu).
+    ///
+    /// Both the separate (`-C target-cpu=native`) and the joined (`-Ctarget-cpu=native`,
+    /// `--codegen=target-cpu=native`) forms are recognised. Other flags are ignored.
+    pub fn with_rustflags<S: AsRef<str>>(&self, flags: &[S]) -> Result<TargetInfo, Error> {
+        let mut target = self.clone();
+        let mut switches = target.switches.into_owned();
+        let mut other_keys = target.other_keys.into_owned();
+
+        let mut flags = flags.iter().map(|f| f.as_ref());
+        while let Some(flag) = flags.next() {
+            let (cfg, codegen) = match flag {
+                "--cfg" => (flags.next(), None),
+                "-C" | "--codegen" => (None, flags.next()),
+                _ if flag.starts_with("--cfg=") => (Some(&flag[6..]), None),
+                _ if flag.starts_with("--codegen=") => (None, Some(&flag[10..])),
+                _ if flag.starts_with("-C") => (None, Some(&flag[2..])),
+                _ => continue,
+            };
+            if let Some(cfg) = cfg {
+                match try!(cfg.parse()) {
+                    Cfg::Name(name) => {
+                        if !switches.iter().any(|s| *s == name) {
+                            switches.push(Cow::Owned(name));
+                        }
+                    }
+                    Cfg::KeyValue(key, value) => {
+                        if !other_keys.iter().any(|t| t.0 == key && t.1 == value) {
+                            other_keys.push((Cow::Owned(key), Cow::Owned(value)));
+                        }
+                    }
+                    _ => return Err(Error::InvalidCfg),
+                }
+            }
+            let mut codegen = codegen.unwrap_or("").splitn(2, '=');
+            match (codegen.next(), codegen.next()) {
+                (Some("target-feature"), Some(features)) => {
+                    for feature in features.split(',').filter(|f| !f.is_empty()) {
+                        let (enable, name) = match feature.as_bytes()[0] {
+                            b'+' => (true, &feature[1..]),
+                            b'-' => (false, &feature[1..]),
+                            _ => (true, feature),
+                        };
+                        let present = other_keys.iter().any(|t| {
+                            t.0 == "target_feature" && t.1 == name
+                        });
+                        if enable && !present {
+                            other_keys.push((Cow::Borrowed("target_feature"),
+                                             Cow::Owned(name.to_owned())));
+                        } else if !enable {
+                            other_keys.retain(|t| t.0 != "target_feature" || t.1 != name);
+                        }
+                    }
+                }
+                (Some("target-cpu"), Some(cpu)) => target.cpu = Some(cpu.to_owned()),
+                _ => {}
+            }
+        }
+
+        target.switches = Cow::Owned(switches);
+        target.other_keys = Cow::Owned(other_keys);
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_rustflags;
+    use TargetInfo;
+
+    #[test]
+    fn split() {
+        assert_eq!(split_rustflags(Some("--cfg\x1ffoo=\"a b\"".into()), Some("-Cfoo".into())),
+                   ["--cfg", "foo=\"a b\""]);
+        assert!(split_rustflags(Some("".into()), Some("-Cfoo".into())).is_empty());
+        assert_eq!(split_rustflags(None, Some(" -C  target-cpu=native ".into())),
+                   ["-C", "target-cpu=native"]);
+        assert!(split_rustflags(None, None).is_empty());
+    }
+
+    #[test]
+    fn with_rustflags() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            assert_eq!(ti.target_cpu(), None);
+            let eff = ti.with_rustflags(&["--cfg", "foo", "--cfg=bar=\"baz\"", "-O",
+                                          "-C", "target-feature=+avx2,-sse2",
+                                          "-Ctarget-cpu=native"]).unwrap();
+            assert!(eff.target_cfg("foo"));
+            assert!(eff.target_cfg("unix"));
+            assert_eq!(eff.target_cfg_value("bar"), Some("baz"));
+            let features = eff.target_cfg_values("target_feature");
+            assert!(features.contains(&"avx2"));
+            assert!(!features.contains(&"sse2"));
+            assert_eq!(eff.target_cpu(), Some("native"));
+
+            let eff = eff.with_rustflags(&["--codegen=target-feature=-avx2"]).unwrap();
+            assert!(!eff.target_cfg_values("target_feature").contains(&"avx2"));
+            assert!(ti.with_rustflags(&["--cfg", "not(foo)"]).is_err());
+        }
+    }
+
+    #[test]
+    fn crt_static() {
+        let none: &[&str] = &[];
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-musl") {
+            assert!(ti.crt_static_with(none).unwrap());
+            assert!(!ti.crt_static_with(&["-C", "target-feature=-crt-static"]).unwrap());
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-msvc") {
+            assert!(!ti.crt_static_with(none).unwrap());
+            assert!(ti.crt_static_with(&["-Ctarget-feature=+crt-static"]).unwrap());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn crt_static_json() {
+        let ti = TargetInfo::from_json_str(r#"{
+            "base-target": "x86_64-unknown-linux-musl",
+            "crt-static-default": false,
+            "crt-static-respected": false
+        }"#).unwrap();
+        assert!(!ti.crt_static_with(&["-Ctarget-feature=+crt-static"]).unwrap());
+    }
+}
@@ -0,0 +1,837 @@
+//! C toolchain used to build native code for the target
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use codegen::write_out_file;
+use {Error, TargetInfo};
+
+/// API level assumed for Android targets
+///
+/// This is the lowest API level supported by the NDK for 64-bit targets.
+const ANDROID_API_LEVEL: u32 = 21;
+
+/// Family of C toolchains, which determines how the tools are named
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Flavor {
+    /// GNU binutils and GCC, prefixed with the triple, e.g. `arm-linux-gnueabihf-gcc`
+    Gnu,
+    /// Android NDK, with a prefixed clang and unprefixed LLVM binutils
+    AndroidNdk,
+    /// Apple’s clang, which handles all the targets with the same tools
+    Apple,
+    /// Microsoft Visual C++
+    Msvc,
+}
+
+/// RISC-V architecture without the extensions, e.g. `riscv64` for `riscv64gc`
+fn riscv_base(arch: &str) -> &str {
+    arch.get(..7).unwrap_or(arch)
+}
+
+impl TargetInfo {
+    /// Architecture component of the triple, e.g. `armv7` for `armv7-unknown-linux-gnueabihf`
+    fn triple_arch(&self) -> String {
+        let triple = self.llvm_triple();
+        triple.split('-').next().unwrap_or("").to_owned()
+    }
+
+    fn flavor(&self) -> Flavor {
+        match (self.target_os(), self.target_env()) {
+            ("windows", "msvc") => Flavor::Msvc,
+            ("android", _) => Flavor::AndroidNdk,
+            ("macos", _) | ("ios", _) | ("tvos", _) | ("watchos", _) | ("visionos", _) => {
+                Flavor::Apple
+            }
+            _ => Flavor::Gnu,
+        }
+    }
+
+    /// Triple used by GCC and clang for the target
+    ///
+    /// This often differs from the Rust target triple. For example, the C compiler triple for
+    /// `armv7-unknown-linux-gnueabihf` is `arm-linux-gnueabihf` and the one for
+    /// `x86_64-pc-windows-gnu` is `x86_64-w64-mingw32`. Android triples include the API level,
+    /// which is assumed to be 21, as expected by the clang wrappers of the NDK.
+    ///
+    /// For targets that are not known, the target triple (or `llvm-target` of custom JSON
+    /// targets) is returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("x86_64-pc-windows-gnu").expect("target info");
+    /// assert_eq!(target.c_compiler_triple(), "x86_64-w64-mingw32");
+    /// ```
+    pub fn c_compiler_triple(&self) -> String {
+        let triple = self.llvm_triple();
+        let arch = self.triple_arch();
+        let abi = triple.rsplit('-').next().unwrap_or("");
+        let is_arm = self.target_arch() == "arm";
+        let is_x86 = self.target_arch() == "x86" || self.target_arch() == "x86_64";
+        match self.target_os() {
+            "linux" if abi.starts_with(self.target_env()) && !self.target_env().is_empty() => {
+                let arch = if is_arm { "arm" } else { &*arch };
+                let arch = if arch.starts_with("riscv") { riscv_base(arch) } else { arch };
+                format!("{}-linux-{}", arch, abi)
+            }
+            "android" => {
+                let arch = match &*arch {
+                    "armv7" | "thumbv7neon" => "armv7a",
+                    _ if is_arm => "arm",
+                    arch => arch,
+                };
+                let abi = if is_arm { "androideabi" } else { "android" };
+                format!("{}-linux-{}{}", arch, abi, ANDROID_API_LEVEL)
+            }
+            // Both MinGW and the `*-pc-windows-gnullvm` targets, for which llvm-mingw uses the
+            // same triple
+            "windows" if self.target_env() == "gnu" => {
+                let arch = if self.target_arch() == "x86" { "i686" } else { &*arch };
+                format!("{}-w64-mingw32", arch)
+            }
+            "windows" => {
+                let arch = if self.target_arch() == "x86" { "i686" } else { &*arch };
+                format!("{}-pc-windows-{}", arch, self.target_env())
+            }
+            "macos" | "ios" | "tvos" | "watchos" | "visionos" => {
+                let arch = if arch == "aarch64" { "arm64" } else { &*arch };
+                let os = if self.target_os() == "macos" { "darwin" } else { self.target_os() };
+                let apple_abi = match self.target_cfg_value("target_abi") {
+                    Some(abi) if !abi.is_empty() => abi,
+                    _ => self.target_env(),
+                };
+                // Older rustc does not report the `sim` ABI of the x86 simulator targets
+                let suffix = match apple_abi {
+                    "macabi" => "-macabi",
+                    "sim" => "-simulator",
+                    _ if self.target_os() != "macos" && is_x86 => "-simulator",
+                    _ => "",
+                };
+                format!("{}-apple-{}{}", arch, os, suffix)
+            }
+            "netbsd" if self.target_vendor() == Some("unknown") => format!("{}--netbsd", arch),
+            "freebsd" | "openbsd" | "dragonfly" | "bitrig" | "netbsd" => {
+                format!("{}-{}-{}", arch, self.target_vendor().unwrap_or("unknown"),
+                        self.target_os())
+            }
+            "solaris" | "illumos" => {
+                format!("{}-{}-solaris2.11", arch, if is_x86 { "pc" } else { "sun" })
+            }
+            "none" if is_arm => String::from("arm-none-eabi"),
+            "none" if arch.starts_with("riscv") => format!("{}-unknown-elf", riscv_base(&arch)),
+            "none" => format!("{}-none-elf", arch),
+            _ => triple.into_owned(),
+        }
+    }
+
+    /// Target triple understood by clang, suitable for `--target=`
+    ///
+    /// This is the target triple (or `llvm-target` of custom JSON targets), with the Rust
+    /// specific parts replaced: the RISC-V extensions are removed from the architecture, Apple
+    /// targets use the same triple as `c_compiler_triple`, `wasm32-wasip1` becomes
+    /// `wasm32-wasi` and `x86_64-pc-windows-gnullvm` becomes `x86_64-w64-windows-gnu`.
+    pub fn clang_target(&self) -> String {
+        if self.flavor() == Flavor::Apple {
+            return self.c_compiler_triple();
+        }
+        let triple = self.llvm_triple();
+        let arch = self.triple_arch();
+        let rest = &triple[arch.len()..];
+        let arch = if arch.starts_with("riscv") { riscv_base(&arch) } else { &*arch };
+        match rest {
+            "-wasip1" | "-wasip1-threads" => format!("{}-wasi{}", arch, &rest[7..]),
+            "-pc-windows-gnullvm" => format!("{}-w64-windows-gnu", arch),
+            _ => format!("{}{}", arch, rest),
+        }
+    }
+
+    /// Arguments for clang to compile or parse C code for the target
+    ///
+    /// This contains `--target={clang_target}` and, when the `sysroot` of the target system is
+    /// known, `--sysroot={sysroot}` and `-isystem {sysroot}/usr/include`.
+    ///
+    /// # Examples
+    ///
+    /// Setting up bindgen in `build.rs`:
+    ///
+    /// ```rust,ignore
+    /// let target = TargetInfo::new().expect("target info");
+    /// let bindings = bindgen::Builder::default()
+    ///     .header("wrapper.h")
+    ///     .clang_args(target.clang_args(Some(Path::new("/usr/aarch64-linux-gnu"))))
+    ///     .generate();
+    /// ```
+    pub fn clang_args(&self, sysroot: Option<&Path>) -> Vec<String> {
+        let mut args = vec![format!("--target={}", self.clang_target())];
+        if let Some(sysroot) = sysroot {
+            args.push(format!("--sysroot={}", sysroot.display()));
+            args.push(String::from("-isystem"));
+            args.push(sysroot.join("usr").join("include").display().to_string());
+        }
+        args
+    }
+
+    /// Name of the C compiler for the target
+    ///
+    /// This is `{c_compiler_triple}-gcc` for most targets, `{c_compiler_triple}-clang` for
+    /// Android NDK, `clang` for Apple targets and `cl.exe` for MSVC. Note that the unprefixed
+    /// `cc` is usually used when the target is the same as the host.
+    pub fn c_compiler_tool(&self) -> String {
+        match self.flavor() {
+            Flavor::Gnu => format!("{}-gcc", self.c_compiler_triple()),
+            Flavor::AndroidNdk => format!("{}-clang", self.c_compiler_triple()),
+            Flavor::Apple => String::from("clang"),
+            Flavor::Msvc => String::from("cl.exe"),
+        }
+    }
+
+    /// Name of the C++ compiler for the target
+    ///
+    /// This is `{c_compiler_triple}-g++` for most targets, `{c_compiler_triple}-clang++` for
+    /// Android NDK, `clang++` for Apple targets and `cl.exe` for MSVC.
+    pub fn cxx_compiler_tool(&self) -> String {
+        match self.flavor() {
+            Flavor::Gnu => format!("{}-g++", self.c_compiler_triple()),
+            Flavor::AndroidNdk => format!("{}-clang++", self.c_compiler_triple()),
+            Flavor::Apple => String::from("clang++"),
+            Flavor::Msvc => String::from("cl.exe"),
+        }
+    }
+
+    /// Name of the static library archiver for the target
+    ///
+    /// This is `{c_compiler_triple}-ar` for most targets, `llvm-ar` for Android NDK, `ar` for
+    /// Apple targets and `lib.exe` for MSVC.
+    pub fn ar_tool(&self) -> String {
+        self.binutils_tool("ar").unwrap_or(String::from("lib.exe"))
+    }
+
+    /// Name of the `ranlib` for the target
+    ///
+    /// Returns `None` for MSVC, which does not need one.
+    pub fn ranlib_tool(&self) -> Option<String> {
+        self.binutils_tool("ranlib")
+    }
+
+    /// Name of the `strip` for the target
+    ///
+    /// Returns `None` for MSVC, which does not have one.
+    pub fn strip_tool(&self) -> Option<String> {
+        self.binutils_tool("strip")
+    }
+
+    /// Canonical GNU triple of the target, suitable for `./configure --host=`
+    ///
+    /// The triple is in the `cpu-vendor-os` form produced by `config.sub`. For example,
+    /// `armv7-linux-androideabi` becomes `armv7-unknown-linux-androideabi`,
+    /// `x86_64-pc-windows-gnu` becomes `x86_64-w64-mingw32` and `riscv64gc-unknown-linux-gnu`
+    /// becomes `riscv64-unknown-linux-gnu`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::from_str("aarch64-linux-android").expect("target info");
+    /// assert_eq!(target.autotools_host(), "aarch64-unknown-linux-android");
+    /// ```
+    pub fn autotools_host(&self) -> String {
+        let triple = self.llvm_triple();
+        let abi = triple.rsplit('-').next().unwrap_or("");
+        let arch = self.triple_arch();
+        let cpu = match self.target_arch() {
+            "aarch64" => "aarch64",
+            "riscv32" | "riscv64" => riscv_base(&arch),
+            "arm" if arch.starts_with("thumb") => "arm",
+            _ => &*arch,
+        };
+        let vendor = self.target_vendor().unwrap_or("unknown");
+        let env = self.target_env();
+        match self.target_os() {
+            "linux" if abi.starts_with(env) && !env.is_empty() => {
+                format!("{}-{}-linux-{}", cpu, vendor, abi)
+            }
+            "android" => {
+                let abi = if self.target_arch() == "arm" { "androideabi" } else { "android" };
+                format!("{}-{}-linux-{}", cpu, vendor, abi)
+            }
+            // MinGW and the `*-pc-windows-gnullvm` targets are both `*-w64-mingw32`
+            "windows" if env == "gnu" => self.c_compiler_triple(),
+            "macos" => format!("{}-apple-darwin", cpu),
+            "ios" | "tvos" | "watchos" | "visionos" => {
+                format!("{}-apple-{}", cpu, self.target_os())
+            }
+            "solaris" | "illumos" | "none" => self.c_compiler_triple(),
+            "wasi" => format!("{}-unknown-wasi", cpu),
+            "vxworks" => format!("{}-{}-vxworks", cpu, vendor),
+            // Only the environments known to `config.sub` are kept, e.g. `relibc` of Redox is not
+            os if ["gnu", "musl", "eabi"].iter().any(|e| env.starts_with(e)) => {
+                format!("{}-{}-{}-{}", cpu, vendor, os, env)
+            }
+            os => format!("{}-{}-{}", cpu, vendor, os),
+        }
+    }
+
+    /// Environment variables specifying the C toolchain to `./configure`
+    ///
+    /// Contains `CC`, `CXX`, `AR` and, when the target has them, `RANLIB` and `STRIP`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::process::Command;
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("target info");
+    /// let status = Command::new("./configure")
+    ///     .arg(format!("--host={}", target.autotools_host()))
+    ///     .envs(target.autotools_env())
+    ///     .status();
+    /// ```
+    pub fn autotools_env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("CC", self.c_compiler_tool()),
+            ("CXX", self.cxx_compiler_tool()),
+            ("AR", self.ar_tool()),
+        ];
+        if let Some(ranlib) = self.ranlib_tool() {
+            env.push(("RANLIB", ranlib));
+        }
+        if let Some(strip) = self.strip_tool() {
+            env.push(("STRIP", strip));
+        }
+        env
+    }
+
+    fn binutils_tool(&self, tool: &str) -> Option<String> {
+        match self.flavor() {
+            Flavor::Gnu => Some(format!("{}-{}", self.c_compiler_triple(), tool)),
+            Flavor::AndroidNdk => Some(format!("llvm-{}", tool)),
+            Flavor::Apple => Some(String::from(tool)),
+            Flavor::Msvc => None,
+        }
+    }
+}
+
+/// `CMAKE_SYSTEM_NAME` for the target
+fn cmake_system_name(os: &str) -> &str {
+    match os {
+        "linux" => "Linux",
+        "android" => "Android",
+        "windows" => "Windows",
+        "macos" => "Darwin",
+        "ios" => "iOS",
+        "tvos" => "tvOS",
+        "watchos" => "watchOS",
+        "visionos" => "visionOS",
+        "freebsd" => "FreeBSD",
+        "netbsd" => "NetBSD",
+        "openbsd" => "OpenBSD",
+        "dragonfly" => "DragonFly",
+        "solaris" | "illumos" => "SunOS",
+        "haiku" => "Haiku",
+        "fuchsia" => "Fuchsia",
+        "emscripten" => "Emscripten",
+        "wasi" => "WASI",
+        "none" | "unknown" => "Generic",
+        os => os,
+    }
+}
+
+/// Quote the string for use as an argument in CMake scripts
+fn cmake_quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' | '"' | '$' => { quoted.push('\\'); quoted.push(c) }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl TargetInfo {
+    /// Processor name as reported by `uname -m` (or `PROCESSOR_ARCHITECTURE` on Windows)
+    fn cmake_system_processor(&self) -> String {
+        let arch = self.triple_arch();
+        match (self.target_os(), self.target_arch()) {
+            ("windows", "x86_64") => String::from("AMD64"),
+            ("windows", "x86") => String::from("x86"),
+            ("windows", "aarch64") => String::from("ARM64"),
+            ("macos", "aarch64") | ("ios", "aarch64") => String::from("arm64"),
+            (_, "powerpc") => String::from("ppc"),
+            (_, "powerpc64") if arch.ends_with("le") => String::from("ppc64le"),
+            (_, "powerpc64") => String::from("ppc64"),
+            (_, "riscv32") | (_, "riscv64") => riscv_base(&arch).to_owned(),
+            _ => arch,
+        }
+    }
+}
+
+/// Generator of CMake toolchain files
+///
+/// The toolchain file sets `CMAKE_SYSTEM_NAME`, `CMAKE_SYSTEM_PROCESSOR`, the compilers and
+/// binutils (see `TargetInfo::c_compiler_tool` and friends), as well as
+/// `CMAKE_{C,CXX}_COMPILER_TARGET` to the `TargetInfo::clang_target`, which CMake passes to
+/// clang as `--target`. When a sysroot is given, `CMAKE_SYSROOT` is set and CMake is configured
+/// to only look for libraries and headers inside of it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use target_build_utils::{TargetInfo, CMakeToolchain};
+/// let target = TargetInfo::new().expect("could not get target");
+/// let toolchain = CMakeToolchain::new(&target)
+///     .sysroot("/usr/arm-linux-gnueabihf")
+///     .write("toolchain.cmake")
+///     .expect("could not write the toolchain file");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CMakeToolchain<'a> {
+    target: &'a TargetInfo,
+    c_compiler: Option<String>,
+    cxx_compiler: Option<String>,
+    sysroot: Option<PathBuf>,
+}
+
+impl<'a> CMakeToolchain<'a> {
+    /// Create a toolchain file generator for the target
+    pub fn new(target: &'a TargetInfo) -> CMakeToolchain<'a> {
+        CMakeToolchain {
+            target: target,
+            c_compiler: None,
+            cxx_compiler: None,
+            sysroot: None,
+        }
+    }
+
+    /// Use the specified C compiler instead of `TargetInfo::c_compiler_tool`
+    pub fn c_compiler(&mut self, compiler: &str) -> &mut CMakeToolchain<'a> {
+        self.c_compiler = Some(compiler.into());
+        self
+    }
+
+    /// Use the specified C++ compiler instead of `TargetInfo::cxx_compiler_tool`
+    pub fn cxx_compiler(&mut self, compiler: &str) -> &mut CMakeToolchain<'a> {
+        self.cxx_compiler = Some(compiler.into());
+        self
+    }
+
+    /// Set the sysroot of the target system
+    pub fn sysroot<P: AsRef<Path>>(&mut self, sysroot: P) -> &mut CMakeToolchain<'a> {
+        self.sysroot = Some(sysroot.as_ref().to_path_buf());
+        self
+    }
+
+    /// Contents of the toolchain file
+    pub fn generate(&self) -> String {
+        let target = self.target;
+        let mut out = String::new();
+        let w = &mut out;
+        let mut set = |name: &str, value: &str| {
+            let _ = writeln!(w, "set({} {})", name, cmake_quote(value));
+        };
+        set("CMAKE_SYSTEM_NAME", cmake_system_name(target.target_os()));
+        set("CMAKE_SYSTEM_PROCESSOR", &target.cmake_system_processor());
+        set("CMAKE_C_COMPILER", &self.c_compiler.clone().unwrap_or(target.c_compiler_tool()));
+        set("CMAKE_CXX_COMPILER",
+            &self.cxx_compiler.clone().unwrap_or(target.cxx_compiler_tool()));
+        if target.flavor() != Flavor::Msvc {
+            set("CMAKE_C_COMPILER_TARGET", &target.clang_target());
+            set("CMAKE_CXX_COMPILER_TARGET", &target.clang_target());
+        }
+        set("CMAKE_AR", &target.ar_tool());
+        if let Some(ranlib) = target.ranlib_tool() {
+            set("CMAKE_RANLIB", &ranlib);
+        }
+        if let Some(strip) = target.strip_tool() {
+            set("CMAKE_STRIP", &strip);
+        }
+        if target.target_os() == "none" {
+            set("CMAKE_TRY_COMPILE_TARGET_TYPE", "STATIC_LIBRARY");
+        }
+        if let Some(ref sysroot) = self.sysroot {
+            let sysroot = sysroot.to_string_lossy().replace('\\', "/");
+            set("CMAKE_SYSROOT", &sysroot);
+            set("CMAKE_FIND_ROOT_PATH", &sysroot);
+            set("CMAKE_FIND_ROOT_PATH_MODE_PROGRAM", "NEVER");
+            set("CMAKE_FIND_ROOT_PATH_MODE_LIBRARY", "ONLY");
+            set("CMAKE_FIND_ROOT_PATH_MODE_INCLUDE", "ONLY");
+            set("CMAKE_FIND_ROOT_PATH_MODE_PACKAGE", "ONLY");
+        }
+        out
+    }
+
+    /// Write the toolchain file into `OUT_DIR`
+    ///
+    /// Returns the path to the written file, which can be passed to CMake via
+    /// `-DCMAKE_TOOLCHAIN_FILE=`.
+    pub fn write(&self, file_name: &str) -> Result<PathBuf, Error> {
+        write_out_file(file_name, &self.generate())
+    }
+}
+
+/// `system` of the Meson machine for the target
+fn meson_system(os: &str) -> &str {
+    match os {
+        "macos" | "ios" | "tvos" | "watchos" | "visionos" => "darwin",
+        "solaris" | "illumos" => "sunos",
+        os => os,
+    }
+}
+
+/// `cpu_family` of the Meson machine for the target architecture
+fn meson_cpu_family(arch: &str) -> &str {
+    match arch {
+        "powerpc" => "ppc",
+        "powerpc64" => "ppc64",
+        "mips32r6" => "mips",
+        "mips64r6" => "mips64",
+        "arm64ec" => "aarch64",
+        arch => arch,
+    }
+}
+
+/// Quote the string for use in Meson machine files
+fn meson_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Generator of Meson cross files
+///
+/// The cross file describes the target as the `[host_machine]` and lists the C toolchain (see
+/// `TargetInfo::c_compiler_tool` and friends) in the `[binaries]` section.
+///
+/// The `system` is the `target_os`, except for Apple targets, which are `darwin`, and Solaris
+/// and illumos, which are `sunos`. The `cpu_family` is derived from the `target_arch`:
+///
+/// | `target_arch`                  | `cpu_family`  |
+/// |--------------------------------|---------------|
+/// | `powerpc`                      | `ppc`         |
+/// | `powerpc64`                    | `ppc64`       |
+/// | `mips32r6`                     | `mips`        |
+/// | `mips64r6`                     | `mips64`      |
+/// | `arm64ec`                      | `aarch64`     |
+/// | `x86`, `x86_64`, `arm`,        | `target_arch` |
+/// | `aarch64`, `mips`, `mips64`,   |               |
+/// | `riscv32`, `riscv64`, `s390x`, |               |
+/// | `sparc`, `sparc64`, `wasm32`,  |               |
+/// | `loongarch64` and all others   |               |
+///
+/// The `cpu` is the architecture component of the target triple, e.g. `armv7` for
+/// `armv7-unknown-linux-gnueabihf`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use target_build_utils::{TargetInfo, MesonCrossFile};
+/// let target = TargetInfo::new().expect("could not get target");
+/// let cross_file = MesonCrossFile::new(&target)
+///     .write("cross.ini")
+///     .expect("could not write the cross file");
+/// ```
+#[derive(Clone, Debug)]
+pub struct MesonCrossFile<'a> {
+    target: &'a TargetInfo,
+    c_compiler: Option<String>,
+    cxx_compiler: Option<String>,
+    sysroot: Option<PathBuf>,
+}
+
+impl<'a> MesonCrossFile<'a> {
+    /// Create a cross file generator for the target
+    pub fn new(target: &'a TargetInfo) -> MesonCrossFile<'a> {
+        MesonCrossFile {
+            target: target,
+            c_compiler: None,
+            cxx_compiler: None,
+            sysroot: None,
+        }
+    }
+
+    /// Use the specified C compiler instead of `TargetInfo::c_compiler_tool`
+    pub fn c_compiler(&mut self, compiler: &str) -> &mut MesonCrossFile<'a> {
+        self.c_compiler = Some(compiler.into());
+        self
+    }
+
+    /// Use the specified C++ compiler instead of `TargetInfo::cxx_compiler_tool`
+    pub fn cxx_compiler(&mut self, compiler: &str) -> &mut MesonCrossFile<'a> {
+        self.cxx_compiler = Some(compiler.into());
+        self
+    }
+
+    /// Set the sysroot of the target system
+    ///
+    /// It is written as the `sys_root` property.
+    pub fn sysroot<P: AsRef<Path>>(&mut self, sysroot: P) -> &mut MesonCrossFile<'a> {
+        self.sysroot = Some(sysroot.as_ref().to_path_buf());
+        self
+    }
+
+    /// Contents of the cross file
+    pub fn generate(&self) -> String {
+        let target = self.target;
+        let mut out = String::new();
+        {
+            let w = &mut out;
+            let _ = writeln!(w, "[binaries]");
+            let set = |w: &mut String, name: &str, value: &str| {
+                let _ = writeln!(w, "{} = {}", name, meson_quote(value));
+            };
+            set(w, "c", &self.c_compiler.clone().unwrap_or(target.c_compiler_tool()));
+            set(w, "cpp", &self.cxx_compiler.clone().unwrap_or(target.cxx_compiler_tool()));
+            set(w, "ar", &target.ar_tool());
+            if let Some(strip) = target.strip_tool() {
+                set(w, "strip", &strip);
+            }
+
+            if let Some(ref sysroot) = self.sysroot {
+                let _ = writeln!(w, "\n[properties]");
+                set(w, "sys_root", &sysroot.to_string_lossy());
+            }
+
+            let _ = writeln!(w, "\n[host_machine]");
+            set(w, "system", meson_system(target.target_os()));
+            set(w, "cpu_family", meson_cpu_family(target.target_arch()));
+            set(w, "cpu", &target.triple_arch());
+            set(w, "endian", target.target_endian());
+        }
+        out
+    }
+
+    /// Write the cross file into `OUT_DIR`
+    ///
+    /// Returns the path to the written file, which can be passed to Meson via `--cross-file`.
+    pub fn write(&self, file_name: &str) -> Result<PathBuf, Error> {
+        write_out_file(file_name, &self.generate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use {CMakeToolchain, MesonCrossFile, TargetInfo};
+
+    #[test]
+    fn c_compiler_triples() {
+        macro_rules! check_triple {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.c_compiler_triple(), $expected);
+                    }
+                )+
+            }
+        }
+        check_triple!("x86_64-unknown-linux-gnu" => "x86_64-linux-gnu",
+                      "i686-unknown-linux-gnu" => "i686-linux-gnu",
+                      "x86_64-unknown-linux-musl" => "x86_64-linux-musl",
+                      "i686-unknown-linux-musl" => "i686-linux-musl",
+                      "mips-unknown-linux-gnu" => "mips-linux-gnu",
+                      "mipsel-unknown-linux-musl" => "mipsel-linux-musl",
+                      "aarch64-unknown-linux-gnu" => "aarch64-linux-gnu",
+                      "arm-unknown-linux-gnueabi" => "arm-linux-gnueabi",
+                      "arm-unknown-linux-gnueabihf" => "arm-linux-gnueabihf",
+                      "armv7-unknown-linux-gnueabihf" => "arm-linux-gnueabihf",
+                      "powerpc64le-unknown-linux-gnu" => "powerpc64le-linux-gnu",
+                      "riscv64gc-unknown-linux-gnu" => "riscv64-linux-gnu",
+                      "i686-linux-android" => "i686-linux-android21",
+                      "aarch64-linux-android" => "aarch64-linux-android21",
+                      "arm-linux-androideabi" => "arm-linux-androideabi21",
+                      "armv7-linux-androideabi" => "armv7a-linux-androideabi21",
+                      "x86_64-pc-windows-gnu" => "x86_64-w64-mingw32",
+                    "x86_64-pc-windows-gnullvm" => "x86_64-w64-mingw32",
+                    "aarch64-pc-windows-gnullvm" => "aarch64-w64-mingw32",
+                      "i686-pc-windows-gnu" => "i686-w64-mingw32",
+                      "x86_64-pc-windows-gnullvm" => "x86_64-w64-mingw32",
+                      "aarch64-pc-windows-gnullvm" => "aarch64-w64-mingw32",
+                      "x86_64-pc-windows-msvc" => "x86_64-pc-windows-msvc",
+                      "i586-pc-windows-msvc" => "i686-pc-windows-msvc",
+                      "x86_64-unknown-freebsd" => "x86_64-unknown-freebsd",
+                      "x86_64-unknown-openbsd" => "x86_64-unknown-openbsd",
+                      "x86_64-unknown-dragonfly" => "x86_64-unknown-dragonfly",
+                      "x86_64-unknown-netbsd" => "x86_64--netbsd",
+                      "x86_64-rumprun-netbsd" => "x86_64-rumprun-netbsd",
+                      "x86_64-sun-solaris" => "x86_64-pc-solaris2.11",
+                      "x86_64-apple-darwin" => "x86_64-apple-darwin",
+                      "aarch64-apple-darwin" => "arm64-apple-darwin",
+                      "aarch64-apple-ios" => "arm64-apple-ios",
+                      "x86_64-apple-ios" => "x86_64-apple-ios-simulator",
+                      "aarch64-apple-ios-sim" => "arm64-apple-ios-simulator",
+                      "aarch64-apple-ios-macabi" => "arm64-apple-ios-macabi",
+                      "x86_64-apple-ios-macabi" => "x86_64-apple-ios-macabi",
+                      "thumbv7em-none-eabihf" => "arm-none-eabi");
+    }
+
+    #[test]
+    fn clang_args() {
+        macro_rules! check_target {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.clang_target(), $expected);
+                    }
+                )+
+            }
+        }
+        check_target!("x86_64-unknown-linux-gnu" => "x86_64-unknown-linux-gnu",
+                      "aarch64-linux-android" => "aarch64-linux-android",
+                      "riscv64gc-unknown-linux-gnu" => "riscv64-unknown-linux-gnu",
+                      "riscv32imac-unknown-none-elf" => "riscv32-unknown-none-elf",
+                      "thumbv7em-none-eabihf" => "thumbv7em-none-eabihf",
+                      "aarch64-apple-ios" => "arm64-apple-ios",
+                      "aarch64-apple-ios-macabi" => "arm64-apple-ios-macabi",
+                      "x86_64-apple-ios-macabi" => "x86_64-apple-ios-macabi",
+                      "wasm32-wasip1" => "wasm32-wasi",
+                      "x86_64-pc-windows-gnu" => "x86_64-pc-windows-gnu",
+                      "x86_64-pc-windows-gnullvm" => "x86_64-w64-windows-gnu");
+
+        if let Ok(ti) = TargetInfo::from_str("aarch64-unknown-linux-gnu") {
+            assert_eq!(ti.clang_args(None), ["--target=aarch64-unknown-linux-gnu"]);
+            let sysroot = Path::new("/sysroot");
+            assert_eq!(ti.clang_args(Some(sysroot)),
+                       [String::from("--target=aarch64-unknown-linux-gnu"),
+                        String::from("--sysroot=/sysroot"),
+                        String::from("-isystem"),
+                        sysroot.join("usr").join("include").display().to_string()]);
+        }
+    }
+
+    #[test]
+    fn tool_names() {
+        if let Ok(ti) = TargetInfo::from_str("arm-unknown-linux-gnueabihf") {
+            assert_eq!(ti.c_compiler_tool(), "arm-linux-gnueabihf-gcc");
+            assert_eq!(ti.ar_tool(), "arm-linux-gnueabihf-ar");
+            assert_eq!(ti.ranlib_tool().unwrap(), "arm-linux-gnueabihf-ranlib");
+            assert_eq!(ti.strip_tool().unwrap(), "arm-linux-gnueabihf-strip");
+        }
+        if let Ok(ti) = TargetInfo::from_str("aarch64-linux-android") {
+            assert_eq!(ti.c_compiler_tool(), "aarch64-linux-android21-clang");
+            assert_eq!(ti.ar_tool(), "llvm-ar");
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-msvc") {
+            assert_eq!(ti.c_compiler_tool(), "cl.exe");
+            assert_eq!(ti.ar_tool(), "lib.exe");
+            assert_eq!(ti.ranlib_tool(), None);
+            assert_eq!(ti.strip_tool(), None);
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-gnullvm") {
+            assert_eq!(ti.c_compiler_tool(), "x86_64-w64-mingw32-gcc");
+            assert_eq!(ti.ar_tool(), "x86_64-w64-mingw32-ar");
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-apple-darwin") {
+            assert_eq!(ti.c_compiler_tool(), "clang");
+            assert_eq!(ti.strip_tool().unwrap(), "strip");
+        }
+    }
+
+    #[test]
+    fn autotools_hosts() {
+        macro_rules! check_host {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        assert_eq!(ti.autotools_host(), $expected);
+                    }
+                )+
+            }
+        }
+        check_host!("x86_64-unknown-linux-gnu" => "x86_64-unknown-linux-gnu",
+                    "i586-unknown-linux-gnu" => "i586-unknown-linux-gnu",
+                    "x86_64-unknown-linux-musl" => "x86_64-unknown-linux-musl",
+                    "armv7-unknown-linux-gnueabihf" => "armv7-unknown-linux-gnueabihf",
+                    "riscv64gc-unknown-linux-gnu" => "riscv64-unknown-linux-gnu",
+                    "armv7-linux-androideabi" => "armv7-unknown-linux-androideabi",
+                    "aarch64-linux-android" => "aarch64-unknown-linux-android",
+                    "x86_64-pc-windows-gnu" => "x86_64-w64-mingw32",
+                    "x86_64-unknown-freebsd" => "x86_64-unknown-freebsd",
+                    "x86_64-unknown-netbsd" => "x86_64-unknown-netbsd",
+                    "x86_64-apple-darwin" => "x86_64-apple-darwin",
+                    "aarch64-apple-darwin" => "aarch64-apple-darwin",
+                    "aarch64-apple-ios" => "aarch64-apple-ios",
+                    "wasm32-wasip1" => "wasm32-unknown-wasi",
+                    "aarch64-unknown-nto-qnx710" => "aarch64-unknown-nto",
+                    "x86_64-unknown-redox" => "x86_64-unknown-redox",
+                    "x86_64-wrs-vxworks" => "x86_64-wrs-vxworks",
+                    "x86_64-unknown-hurd-gnu" => "x86_64-unknown-hurd-gnu",
+                    "thumbv7em-none-eabihf" => "arm-none-eabi");
+        if let Ok(ti) = TargetInfo::from_str("aarch64-unknown-linux-gnu") {
+            assert_eq!(ti.autotools_env(), [("CC", String::from("aarch64-linux-gnu-gcc")),
+                                            ("CXX", String::from("aarch64-linux-gnu-g++")),
+                                            ("AR", String::from("aarch64-linux-gnu-ar")),
+                                            ("RANLIB", String::from("aarch64-linux-gnu-ranlib")),
+                                            ("STRIP", String::from("aarch64-linux-gnu-strip"))]);
+        }
+    }
+
+    #[test]
+    fn cmake_toolchain() {
+        if let Ok(ti) = TargetInfo::from_str("armv7-unknown-linux-gnueabihf") {
+            let toolchain = CMakeToolchain::new(&ti).sysroot("/usr/arm-linux-gnueabihf")
+                                                     .generate();
+            for line in &["set(CMAKE_SYSTEM_NAME \"Linux\")",
+                          "set(CMAKE_SYSTEM_PROCESSOR \"armv7\")",
+                          "set(CMAKE_C_COMPILER \"arm-linux-gnueabihf-gcc\")",
+                          "set(CMAKE_CXX_COMPILER \"arm-linux-gnueabihf-g++\")",
+                          "set(CMAKE_C_COMPILER_TARGET \"armv7-unknown-linux-gnueabihf\")",
+                          "set(CMAKE_SYSROOT \"/usr/arm-linux-gnueabihf\")",
+                          "set(CMAKE_FIND_ROOT_PATH_MODE_LIBRARY \"ONLY\")"] {
+                assert!(toolchain.lines().any(|l| l == *line), "{} missing in:\n{}", line,
+                        toolchain);
+            }
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-msvc") {
+            let toolchain = CMakeToolchain::new(&ti).c_compiler("clang-cl").generate();
+            assert!(toolchain.contains("set(CMAKE_SYSTEM_NAME \"Windows\")\n"));
+            assert!(toolchain.contains("set(CMAKE_SYSTEM_PROCESSOR \"AMD64\")\n"));
+            assert!(toolchain.contains("set(CMAKE_C_COMPILER \"clang-cl\")\n"));
+            assert!(!toolchain.contains("CMAKE_SYSROOT"));
+        }
+    }
+
+    #[test]
+    fn meson_cross_file() {
+        if let Ok(ti) = TargetInfo::from_str("powerpc64le-unknown-linux-gnu") {
+            let cross_file = MesonCrossFile::new(&ti).sysroot("/opt/it's").generate();
+            assert_eq!(cross_file, "[binaries]\n\
+                                    c = 'powerpc64le-linux-gnu-gcc'\n\
+                                    cpp = 'powerpc64le-linux-gnu-g++'\n\
+                                    ar = 'powerpc64le-linux-gnu-ar'\n\
+                                    strip = 'powerpc64le-linux-gnu-strip'\n\
+                                    \n\
+                                    [properties]\n\
+                                    sys_root = '/opt/it\\'s'\n\
+                                    \n\
+                                    [host_machine]\n\
+                                    system = 'linux'\n\
+                                    cpu_family = 'ppc64'\n\
+                                    cpu = 'powerpc64le'\n\
+                                    endian = 'little'\n");
+        }
+        if let Ok(ti) = TargetInfo::from_str("aarch64-apple-darwin") {
+            let cross_file = MesonCrossFile::new(&ti).c_compiler("cc").generate();
+            assert!(cross_file.contains("c = 'cc'\n"));
+            assert!(cross_file.contains("system = 'darwin'\n"));
+            assert!(cross_file.contains("cpu_family = 'aarch64'\n"));
+        }
+        macro_rules! check_cpu_family {
+            ($($str: expr => $expected: expr),+) => {
+                $(
+                    if let Ok(ti) = TargetInfo::from_str($str) {
+                        let cross_file = MesonCrossFile::new(&ti).generate();
+                        let line = format!("cpu_family = '{}'\n", $expected);
+                        assert!(cross_file.contains(&line), "{} missing in:\n{}", line,
+                                cross_file);
+                    }
+                )+
+            }
+        }
+        check_cpu_family!("powerpc-unknown-linux-gnu" => "ppc",
+                          "mipsisa32r6-unknown-linux-gnu" => "mips",
+                          "mipsisa64r6el-unknown-linux-gnuabi64" => "mips64",
+                          "arm64ec-pc-windows-msvc" => "aarch64",
+                          "x86_64-unknown-linux-gnu" => "x86_64");
+    }
+}
@@ -0,0 +1,490 @@
+//! Verification of the binaries built for the target
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use {Error, ObjectFormat, TargetInfo};
+
+/// A difference between a binary and the target it was expected to be built for
+///
+/// See `TargetInfo::verify_binary`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The binary is in a different object format
+    Format { expected: ObjectFormat, found: ObjectFormat },
+    /// The binary is for a different machine, as identified by the ELF `e_machine`, the PE/COFF
+    /// `Machine` or the Mach-O `cputype`
+    Machine { expected: u32, found: u32 },
+    /// The binary is for a different Mach-O `cpusubtype`
+    Subtype { expected: u32, found: u32 },
+    /// The ELF `e_flags` of the binary describe a different ABI, e.g. they lack some of the bits
+    /// expected for the target or specify a different floating point ABI
+    Flags { expected: u32, found: u32 },
+    /// The binary has a different pointer width
+    PointerWidth { expected: u32, found: u32 },
+    /// The binary has a different endianness
+    Endian { expected: &'static str, found: &'static str },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::Format { expected, found } => {
+                write!(fmt, "object format is {:?} instead of {:?}", found, expected)
+            }
+            Mismatch::Machine { expected, found } => {
+                write!(fmt, "machine is {:#x} instead of {:#x}", found, expected)
+            }
+            Mismatch::Subtype { expected, found } => {
+                write!(fmt, "CPU subtype is {} instead of {}", found, expected)
+            }
+            Mismatch::Flags { expected, found } => {
+                write!(fmt, "flags {:#x} do not match the expected {:#x}", found, expected)
+            }
+            Mismatch::PointerWidth { expected, found } => {
+                write!(fmt, "pointer width is {} instead of {}", found, expected)
+            }
+            Mismatch::Endian { expected, found } => {
+                write!(fmt, "endianness is {} instead of {}", found, expected)
+            }
+        }
+    }
+}
+
+/// Identification read from the header of an object file or executable
+#[derive(Clone, Copy, Debug, Default)]
+struct Header {
+    format: Option<ObjectFormat>,
+    machine: Option<u32>,
+    subtype: Option<u32>,
+    flags: Option<u32>,
+    pointer_width: Option<u32>,
+    endian: Option<&'static str>,
+}
+
+// Offsets may come from the file itself, so they are added with `checked_add` to not overflow
+// on 32-bit hosts
+
+fn u16_at(data: &[u8], offset: usize, big: bool) -> Option<u16> {
+    offset.checked_add(2).and_then(|end| data.get(offset..end)).map(|b| {
+        let (hi, lo) = if big { (b[0], b[1]) } else { (b[1], b[0]) };
+        (hi as u16) << 8 | lo as u16
+    })
+}
+
+fn u32_at(data: &[u8], offset: usize, big: bool) -> Option<u32> {
+    let second = offset.checked_add(2).and_then(|offset| u16_at(data, offset, big));
+    match (u16_at(data, offset, big), second) {
+        (Some(first), Some(second)) if big => Some((first as u32) << 16 | second as u32),
+        (Some(first), Some(second)) => Some((second as u32) << 16 | first as u32),
+        _ => None,
+    }
+}
+
+/// PE/COFF `Machine` values recognised in COFF object files, which have no magic number
+const COFF_MACHINES: &'static [u16] = &[0x14c, 0x8664, 0x1c4, 0xaa64, 0xa641];
+
+fn elf_header(data: &[u8]) -> Option<Header> {
+    let (pointer_width, flags_offset) = match data.get(4) {
+        Some(&1) => (32, 36),
+        Some(&2) => (64, 48),
+        _ => return None,
+    };
+    let (endian, big) = match data.get(5) {
+        Some(&1) => ("little", false),
+        Some(&2) => ("big", true),
+        _ => return None,
+    };
+    let (machine, flags) = match (u16_at(data, 18, big), u32_at(data, flags_offset, big)) {
+        (Some(machine), Some(flags)) => (machine, flags),
+        _ => return None,
+    };
+    Some(Header {
+        format: Some(ObjectFormat::Elf),
+        machine: Some(machine as u32),
+        flags: Some(flags),
+        pointer_width: Some(pointer_width),
+        endian: Some(endian),
+        ..Header::default()
+    })
+}
+
+fn macho_header(data: &[u8]) -> Option<Header> {
+    let (pointer_width, endian, big) = match data.get(..4) {
+        Some(&[0xfe, 0xed, 0xfa, 0xce]) => (32, "big", true),
+        Some(&[0xce, 0xfa, 0xed, 0xfe]) => (32, "little", false),
+        Some(&[0xfe, 0xed, 0xfa, 0xcf]) => (64, "big", true),
+        Some(&[0xcf, 0xfa, 0xed, 0xfe]) => (64, "little", false),
+        _ => return None,
+    };
+    let (machine, subtype) = match (u32_at(data, 4, big), u32_at(data, 8, big)) {
+        (Some(machine), Some(subtype)) => (machine, subtype),
+        _ => return None,
+    };
+    Some(Header {
+        format: Some(ObjectFormat::MachO),
+        machine: Some(machine),
+        subtype: Some(subtype & 0x00ff_ffff),
+        pointer_width: Some(pointer_width),
+        endian: Some(endian),
+        ..Header::default()
+    })
+}
+
+fn coff_header(data: &[u8]) -> Option<Header> {
+    let machine = if data.starts_with(b"MZ") {
+        let pe_offset = u32_at(data, 0x3c, false).unwrap_or(0) as usize;
+        let machine_offset = match pe_offset.checked_add(4) {
+            Some(end) if data.get(pe_offset..end) == Some(&b"PE\0\0"[..]) => end,
+            _ => return None,
+        };
+        u16_at(data, machine_offset, false)
+    } else if data.starts_with(&[0, 0, 0xff, 0xff]) {
+        // Short import object in an import library
+        u16_at(data, 6, false)
+    } else {
+        u16_at(data, 0, false).and_then(|m| if COFF_MACHINES.contains(&m) { Some(m) } else {
+            None
+        })
+    };
+    machine.map(|machine| Header {
+        format: Some(ObjectFormat::Coff),
+        machine: Some(machine as u32),
+        ..Header::default()
+    })
+}
+
+fn other_header(data: &[u8]) -> Option<Header> {
+    let (format, pointer_width, endian) = match data.get(..4) {
+        Some(&[0, b'a', b's', b'm']) => (ObjectFormat::Wasm, None, None),
+        Some(&[0x01, 0xdf, _, _]) => (ObjectFormat::Xcoff, Some(32), Some("big")),
+        Some(&[0x01, 0xf7, _, _]) => (ObjectFormat::Xcoff, Some(64), Some("big")),
+        _ => return None,
+    };
+    Some(Header {
+        format: Some(format),
+        pointer_width: pointer_width,
+        endian: endian,
+        ..Header::default()
+    })
+}
+
+/// Names of the archive members which are symbol tables or name tables rather than objects
+fn is_archive_index(name: &[u8]) -> bool {
+    name.starts_with(b"/ ") || name.starts_with(b"// ") || name.starts_with(b"/SYM64/") ||
+    name.starts_with(b"/<ECSYMBOLS>/") || name.starts_with(b"__.SYMDEF")
+}
+
+impl TargetInfo {
+    /// Compare the header with the target
+    fn header_mismatches(&self, header: &Header, mismatches: &mut Vec<Mismatch>) {
+        let mut push = |mismatch| if !mismatches.contains(&mismatch) {
+            mismatches.push(mismatch);
+        };
+        let format = self.object_format();
+        match header.format {
+            Some(found) if found != format => {
+                return push(Mismatch::Format { expected: format, found: found });
+            }
+            _ => {}
+        }
+        let (machine, subtype, flags) = match format {
+            ObjectFormat::Elf => match self.elf_machine() {
+                Some(elf) => (Some(elf.machine as u32), None, Some(elf.flags)),
+                None => (None, None, None),
+            },
+            ObjectFormat::Coff => (self.pe_machine().map(u32::from), None, None),
+            ObjectFormat::MachO => match self.macho_cpu() {
+                Some(cpu) => (Some(cpu.cputype), Some(cpu.cpusubtype), None),
+                None => (None, None, None),
+            },
+            _ => (None, None, None),
+        };
+        if let (Some(expected), Some(found)) = (machine, header.machine) {
+            if expected != found {
+                return push(Mismatch::Machine { expected: expected, found: found });
+            }
+        }
+        if let (Some(expected), Some(found)) = (subtype, header.subtype) {
+            // Binaries for all the subtypes of the CPU type are fine
+            let all = if header.machine.map(|m| m & 0xff) == Some(7) { 3 } else { 0 };
+            if expected != found && found != all {
+                push(Mismatch::Subtype { expected: expected, found: found });
+            }
+        }
+        if let (Some(expected), Some(found)) = (flags, header.flags) {
+            // Bits which have to match exactly rather than be set: `EF_ARM_ABI_FLOAT_HARD` and
+            // the two bit `EF_RISCV_FLOAT_ABI` field
+            let exact = match machine {
+                Some(40) => 0x400,
+                Some(243) => 0x6,
+                _ => 0,
+            };
+            if found & expected & !exact != expected & !exact || found & exact != expected & exact {
+                push(Mismatch::Flags { expected: expected, found: found });
+            }
+        }
+        if let Some(found) = header.pointer_width {
+            let expected = self.target_pointer_width().parse().unwrap_or(found);
+            if expected != found {
+                push(Mismatch::PointerWidth { expected: expected, found: found });
+            }
+        }
+        if let Some(found) = header.endian {
+            let expected = if self.target_endian() == "big" { "big" } else { "little" };
+            if expected != found {
+                push(Mismatch::Endian { expected: expected, found: found });
+            }
+        }
+    }
+
+    /// Compare the binary with the target
+    ///
+    /// Returns `false` if the format of the binary was not recognised.
+    fn binary_mismatches(&self, data: &[u8], mismatches: &mut Vec<Mismatch>) -> bool {
+        if data.starts_with(b"!<arch>\n") {
+            let mut recognised = false;
+            let mut offset: usize = 8;
+            while let Some(header) = offset.checked_add(60)
+                                           .and_then(|end| data.get(offset..end)) {
+                let size = String::from_utf8_lossy(&header[48..58]).trim().parse::<usize>();
+                let size = match size {
+                    Ok(size) => size,
+                    Err(_) => return false,
+                };
+                let start = offset + 60;
+                let next = match start.checked_add(size).and_then(|e| e.checked_add(size % 2)) {
+                    Some(next) => next,
+                    None => return false,
+                };
+                let end = ::std::cmp::min(start + size, data.len());
+                let mut member = &data[start..end];
+                // BSD archives store long names at the start of the member data
+                if header.starts_with(b"#1/") {
+                    let len = String::from_utf8_lossy(&header[3..16]).trim().parse().unwrap_or(0);
+                    member = member.get(len..).unwrap_or(&[]);
+                }
+                if !is_archive_index(&header[..16]) {
+                    recognised |= self.binary_mismatches(member, mismatches);
+                }
+                offset = next;
+            }
+            return recognised;
+        }
+
+        if data.starts_with(&[0xca, 0xfe, 0xba, 0xbe]) {
+            // Universal Mach-O binary, any one of the architectures has to match. Java class
+            // files share the magic number, but have a version of at least 45 in its place.
+            let count = match u32_at(data, 4, true) {
+                Some(count) if count < 45 => count as usize,
+                _ => return false,
+            };
+            if count == 0 || data.len() < 8 + count * 20 {
+                return false;
+            }
+            let mut first = None;
+            for i in 0..count {
+                let entry = 8 + i * 20;
+                let header = Header {
+                    format: Some(ObjectFormat::MachO),
+                    machine: u32_at(data, entry, true),
+                    subtype: u32_at(data, entry + 4, true).map(|s| s & 0x00ff_ffff),
+                    ..Header::default()
+                };
+                let mut arch_mismatches = Vec::new();
+                self.header_mismatches(&header, &mut arch_mismatches);
+                if arch_mismatches.is_empty() {
+                    return true;
+                }
+                first = first.or(Some(arch_mismatches));
+            }
+            return match first {
+                Some(first) => {
+                    mismatches.extend(first);
+                    true
+                }
+                None => false,
+            };
+        }
+
+        let header = if data.starts_with(b"\x7fELF") {
+            elf_header(data)
+        } else {
+            macho_header(data).or_else(|| other_header(data)).or_else(|| coff_header(data))
+        };
+        match header {
+            Some(header) => {
+                self.header_mismatches(&header, mismatches);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check whether the object file, library or executable was built for the target
+    ///
+    /// ELF, PE/COFF, Mach-O (including universal binaries), WebAssembly and XCOFF files are
+    /// recognised, as well as `ar` archives containing those. The format, machine, pointer
+    /// width and endianness found in the headers are compared with the target, and the
+    /// differences are returned. An empty list means the binary matches the target.
+    ///
+    /// `Error::UnknownBinaryFormat` is returned if the format of the file (or of all the
+    /// members of an archive) is not recognised.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use target_build_utils::TargetInfo;
+    /// let target = TargetInfo::new().expect("could not get target");
+    /// for mismatch in target.verify_binary("libfoo.a").expect("could not read libfoo.a") {
+    ///     panic!("libfoo.a was not built for the target: {}", mismatch);
+    /// }
+    /// ```
+    pub fn verify_binary<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Mismatch>, Error> {
+        let mut data = Vec::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_end(&mut data)).map_err(Error::Io));
+        self.verify_binary_bytes(&data)
+    }
+
+    /// Check whether the contents of an object file, library or executable were built for the
+    /// target
+    ///
+    /// See `TargetInfo::verify_binary`.
+    pub fn verify_binary_bytes(&self, data: &[u8]) -> Result<Vec<Mismatch>, Error> {
+        let mut mismatches = Vec::new();
+        if self.binary_mismatches(data, &mut mismatches) {
+            Ok(mismatches)
+        } else {
+            Err(Error::UnknownBinaryFormat)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Mismatch, ObjectFormat, TargetInfo};
+
+    fn elf(class: u8, data: u8, machine: u16, flags: u32) -> Vec<u8> {
+        let mut elf = vec![0; 64];
+        elf[..6].copy_from_slice(&[0x7f, b'E', b'L', b'F', class, data]);
+        let (machine, flags) = if data == 2 {
+            (machine.to_be_bytes(), flags.to_be_bytes())
+        } else {
+            (machine.to_le_bytes(), flags.to_le_bytes())
+        };
+        elf[18..20].copy_from_slice(&machine);
+        let flags_offset = if class == 1 { 36 } else { 48 };
+        elf[flags_offset..flags_offset + 4].copy_from_slice(&flags);
+        elf
+    }
+
+    fn archive(members: &[&[u8]]) -> Vec<u8> {
+        let mut archive = b"!<arch>\n".to_vec();
+        for (i, member) in [&[0u8; 4][..]].iter().chain(members).enumerate() {
+            let name = if i == 0 { String::from("/") } else { format!("m{}.o/", i) };
+            archive.extend(format!("{:16}{:12}{:6}{:6}{:8}{:10}`\n", name, 0, 0, 0, 644,
+                                   member.len()).bytes());
+            archive.extend(member.iter());
+            if member.len() % 2 == 1 {
+                archive.push(b'\n');
+            }
+        }
+        archive
+    }
+
+    #[test]
+    fn verify_elf() {
+        if let Ok(ti) = TargetInfo::from_str("x86_64-unknown-linux-gnu") {
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 62, 0)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 3, 0)).unwrap(),
+                       [Mismatch::Machine { expected: 62, found: 3 }]);
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 62, 0)).unwrap(),
+                       [Mismatch::PointerWidth { expected: 64, found: 32 }]);
+            assert!(ti.verify_binary_bytes(b"not a binary").is_err());
+            assert!(ti.verify_binary_bytes(b"\x7fELF\x02\x01").is_err());
+            assert!(ti.verify_binary_bytes(&elf(2, 1, 62, 0)[..50]).is_err());
+
+            let mut odd = elf(2, 1, 62, 0);
+            odd.push(0);
+            let lib = archive(&[&odd, &elf(2, 1, 62, 0)]);
+            assert_eq!(ti.verify_binary_bytes(&lib).unwrap(), []);
+            let lib = archive(&[&elf(2, 1, 62, 0), &elf(2, 1, 183, 0)]);
+            assert_eq!(ti.verify_binary_bytes(&lib).unwrap(),
+                       [Mismatch::Machine { expected: 62, found: 183 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("armv7-unknown-linux-gnueabihf") {
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 40, 0x0500_0400)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 40, 0x0500_0200)).unwrap(),
+                       [Mismatch::Flags { expected: 0x0500_0400, found: 0x0500_0200 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("armv7-unknown-linux-gnueabi") {
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 40, 0x0500_0200)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(1, 1, 40, 0x0500_0400)).unwrap(),
+                       [Mismatch::Flags { expected: 0x0500_0000, found: 0x0500_0400 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("riscv64gc-unknown-linux-gnu") {
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 243, 0x5)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 243, 0x1)).unwrap(),
+                       [Mismatch::Flags { expected: 0x5, found: 0x1 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("riscv64imac-unknown-none-elf") {
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 243, 0x1)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 243, 0x5)).unwrap(),
+                       [Mismatch::Flags { expected: 0x1, found: 0x5 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("powerpc64-unknown-linux-gnu") {
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 2, 21, 0)).unwrap(), []);
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 21, 0)).unwrap(),
+                       [Mismatch::Endian { expected: "big", found: "little" }]);
+        }
+    }
+
+    #[test]
+    fn verify_coff_and_macho() {
+        let mut pe = vec![0; 0x90];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3c] = 0x80;
+        pe[0x80..0x86].copy_from_slice(b"PE\0\0\x64\x86");
+        if let Ok(ti) = TargetInfo::from_str("x86_64-pc-windows-msvc") {
+            assert_eq!(ti.verify_binary_bytes(&pe).unwrap(), []);
+            let mut far = pe.clone();
+            far[0x3c..0x40].copy_from_slice(&[0xfe, 0xff, 0xff, 0xff]);
+            assert!(ti.verify_binary_bytes(&far).is_err());
+            let import = [0, 0, 0xff, 0xff, 0, 0, 0x4c, 0x01];
+            assert_eq!(ti.verify_binary_bytes(&archive(&[&import])).unwrap(),
+                       [Mismatch::Machine { expected: 0x8664, found: 0x14c }]);
+            assert_eq!(ti.verify_binary_bytes(&elf(2, 1, 62, 0)).unwrap(),
+                       [Mismatch::Format { expected: ObjectFormat::Coff,
+                                           found: ObjectFormat::Elf }]);
+        }
+
+        let macho = [0xcf, 0xfa, 0xed, 0xfe, 0x0c, 0, 0, 0x01, 0x02, 0, 0, 0x80];
+        if let Ok(ti) = TargetInfo::from_str("aarch64-apple-darwin") {
+            assert_eq!(ti.verify_binary_bytes(&macho).unwrap(),
+                       [Mismatch::Subtype { expected: 0, found: 2 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("arm64e-apple-darwin") {
+            assert_eq!(ti.verify_binary_bytes(&macho).unwrap(), []);
+        }
+        let mut fat = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 2];
+        fat.extend(&[0x01, 0, 0, 0x07, 0, 0, 0, 0x03][..]);
+        fat.extend(&[0; 12][..]);
+        fat.extend(&[0x01, 0, 0, 0x0c, 0, 0, 0, 0][..]);
+        fat.extend(&[0; 12][..]);
+        if let Ok(ti) = TargetInfo::from_str("x86_64-apple-darwin") {
+            assert_eq!(ti.verify_binary_bytes(&fat).unwrap(), []);
+        }
+        if let Ok(ti) = TargetInfo::from_str("armv7s-apple-ios") {
+            assert_eq!(ti.verify_binary_bytes(&fat).unwrap(),
+                       [Mismatch::Machine { expected: 12, found: 0x0100_0007 }]);
+        }
+        if let Ok(ti) = TargetInfo::from_str("x86_64-apple-darwin") {
+            assert!(ti.verify_binary_bytes(&fat[..30]).is_err());
+            assert!(ti.verify_binary_bytes(&macho[..6]).is_err());
+            // Java class file, version 52.0
+            assert!(ti.verify_binary_bytes(&[0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52]).is_err());
+        }
+    }
+}